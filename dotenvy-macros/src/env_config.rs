@@ -0,0 +1,288 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, Data, DeriveInput, Field, Fields, GenericArgument, Ident, LitStr, Path,
+    PathArguments, Token, Type,
+};
+
+/// The `#[env(...)]` attributes of a field.
+#[derive(Default)]
+struct FieldAttrs {
+    key: Option<LitStr>,
+    default: Option<Default>,
+    secret: bool,
+    parse_with: Option<Path>,
+    nested: bool,
+    prefix: Option<LitStr>,
+}
+
+enum Default {
+    /// `default`, using `Default::default()`.
+    Trait,
+    /// `default = "value"`, parsed like a value of the variable.
+    Value(LitStr),
+}
+
+impl FieldAttrs {
+    fn parse(field: &Field) -> syn::Result<Self> {
+        let mut attrs = Self::default();
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("env"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("key") {
+                    attrs.key = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    attrs.default = Some(if meta.input.peek(Token![=]) {
+                        Default::Value(meta.value()?.parse()?)
+                    } else {
+                        Default::Trait
+                    });
+                } else if meta.path.is_ident("secret") {
+                    attrs.secret = true;
+                } else if meta.path.is_ident("parse_with") {
+                    attrs.parse_with = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                } else if meta.path.is_ident("nested") {
+                    attrs.nested = true;
+                } else if meta.path.is_ident("prefix") {
+                    attrs.prefix = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unknown attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        if attrs.nested
+            && (attrs.key.is_some() || attrs.default.is_some() || attrs.parse_with.is_some())
+        {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`nested` cannot be used with `key`, `default` or `parse_with`",
+            ));
+        }
+        if attrs.prefix.is_some() && !attrs.nested {
+            return Err(syn::Error::new_spanned(field, "`prefix` requires `nested`"));
+        }
+        Ok(attrs)
+    }
+}
+
+/// Returns `T` if the type is `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if path.qself.is_some() || segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// Whether the type is `bool`, which is parsed like `EnvMap::parse_bool` rather than with `FromStr`.
+fn is_bool(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("bool"))
+}
+
+/// Returns the statement reading a field with `read_var`, and the expression initializing it.
+fn expand_var(
+    attrs: &FieldAttrs,
+    var: &Ident,
+    name: &str,
+    ty: &Type,
+) -> (TokenStream, TokenStream) {
+    let key = attrs
+        .key
+        .as_ref()
+        .map_or_else(|| name.to_uppercase(), LitStr::value);
+    let optional = option_inner(ty);
+    let value_ty = optional.unwrap_or(ty);
+    let parse = match &attrs.parse_with {
+        Some(path) => quote! { #path },
+        None if is_bool(value_ty) => quote! { ::dotenvy::__private::parse_bool },
+        None => quote! { |value: &str| value.parse::<#value_ty>() },
+    };
+    let default = if let Some(Default::Value(value)) = &attrs.default {
+        quote! { ::std::option::Option::Some(#value) }
+    } else {
+        quote! { ::std::option::Option::None }
+    };
+    let default_trait = matches!(attrs.default, Some(Default::Trait));
+    let required = optional.is_none() && !default_trait;
+    let secret = attrs.secret;
+    let read = quote! {
+        let #var = ::dotenvy::__private::read_var(
+            env_map,
+            &[prefix, #key].concat(),
+            #default,
+            #required,
+            #secret,
+            #parse,
+            errors,
+        );
+    };
+    let init = if optional.is_some() {
+        quote! { #var }
+    } else if default_trait {
+        quote! { #var.unwrap_or_default() }
+    } else {
+        quote! { #var? }
+    };
+    (read, init)
+}
+
+/// Returns the statement reading a nested field with its `EnvConfig`, and the expression initializing it.
+///
+/// An `Option` field is `None` if no variable has its prefix.
+fn expand_nested(
+    attrs: &FieldAttrs,
+    var: &Ident,
+    name: &str,
+    ty: &Type,
+) -> (TokenStream, TokenStream) {
+    let prefix = attrs
+        .prefix
+        .as_ref()
+        .map_or_else(|| format!("{}_", name.to_uppercase()), LitStr::value);
+    let read = option_inner(ty).map_or_else(
+        || {
+            quote! {
+                let #var = <#ty as ::dotenvy::EnvConfig>::from_env_map_prefixed(
+                    env_map,
+                    &[prefix, #prefix].concat(),
+                    errors,
+                );
+            }
+        },
+        |inner| {
+            quote! {
+                let #var = {
+                    let prefix = [prefix, #prefix].concat();
+                    if env_map.keys().any(|key| key.starts_with(&prefix)) {
+                        <#inner as ::dotenvy::EnvConfig>::from_env_map_prefixed(
+                            env_map, &prefix, errors,
+                        )
+                        .map(::std::option::Option::Some)
+                    } else {
+                        ::std::option::Option::Some(::std::option::Option::None)
+                    }
+                };
+            }
+        },
+    );
+    (read, quote! { #var? })
+}
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "EnvConfig can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "EnvConfig can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut reads = Vec::new();
+    let mut inits = Vec::new();
+    let mut debug_fields = Vec::new();
+    let mut has_secret = false;
+    for field in &fields.named {
+        let attrs = FieldAttrs::parse(field)?;
+        let ident = field.ident.as_ref().expect("named field");
+        let var = format_ident!("field_{}", ident);
+        let name = var.to_string()["field_".len()..].to_owned();
+        let ty = &field.ty;
+
+        let (read, init) = if attrs.nested {
+            expand_nested(&attrs, &var, &name, ty)
+        } else {
+            expand_var(&attrs, &var, &name, ty)
+        };
+        reads.push(read);
+        inits.push(quote! { #ident: #init });
+
+        has_secret |= attrs.secret;
+        debug_fields.push(if attrs.secret {
+            quote! { .field(#name, &::std::format_args!("[redacted]")) }
+        } else {
+            quote! { .field(#name, &self.#ident) }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let debug = if has_secret {
+        expand_debug(input, &debug_fields)
+    } else {
+        TokenStream::new()
+    };
+    Ok(quote! {
+        impl #impl_generics ::dotenvy::EnvConfig for #ident #ty_generics #where_clause {
+            fn from_env_map_prefixed(
+                env_map: &::dotenvy::EnvMap,
+                prefix: &str,
+                errors: &mut ::std::vec::Vec<::dotenvy::Error>,
+            ) -> ::std::option::Option<Self> {
+                let errors_before = errors.len();
+                #(#reads)*
+                if errors.len() > errors_before {
+                    return ::std::option::Option::None;
+                }
+                ::std::option::Option::Some(Self { #(#inits),* })
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Builds the struct from the variables.
+            ///
+            /// Fails with `Error::Multiple` listing every missing or invalid variable.
+            pub fn from_env_map(
+                env_map: &::dotenvy::EnvMap,
+            ) -> ::std::result::Result<Self, ::dotenvy::Error> {
+                <Self as ::dotenvy::EnvConfig>::from_env_map(env_map)
+            }
+        }
+
+        #debug
+    })
+}
+
+/// Returns the `Debug` implementation redacting secret fields, bounding each type parameter by `Debug`.
+fn expand_debug(input: &DeriveInput, debug_fields: &[TokenStream]) -> TokenStream {
+    let ident = &input.ident;
+    let struct_name = ident.to_string();
+    let mut generics = input.generics.clone();
+    let params = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause
+            .predicates
+            .push(parse_quote! { #param: ::std::fmt::Debug });
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::std::fmt::Debug for #ident #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.debug_struct(#struct_name)
+                    #(#debug_fields)*
+                    .finish()
+            }
+        }
+    }
+}
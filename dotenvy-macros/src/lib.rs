@@ -4,9 +4,11 @@ use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
     parse::{Parse, ParseStream},
-    parse_macro_input, ItemFn, LitBool, LitStr,
+    parse_macro_input, DeriveInput, ItemFn, LitBool, LitStr,
 };
 
+mod env_config;
+
 /// Loads environment variables from a file and modifies the environment.
 ///
 /// Three optional arguments are supported: `path`, `required`, and `override_`.
@@ -85,6 +87,68 @@ pub fn load(attr: TokenStream, item: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Derives `dotenvy::EnvConfig`, building a struct from the variables of an `EnvMap`.
+///
+/// This adds a `from_env_map` constructor that reports every missing or invalid variable at once with `Error::Multiple`.
+/// If a field is `secret`, it also adds a `Debug` implementation redacting it, so `Debug` should not also be derived.
+///
+/// Fields are read from the field name in uppercase and parsed with `FromStr`,
+/// except `bool` fields, which are parsed like `EnvMap::parse_bool` to also accept `yes`, `no`, `on`, `off`, `1` and `0`.
+/// They are configured with `#[env(...)]`:
+///
+/// - `key = "NAME"` reads the field from another key.
+/// - `default = "value"` is parsed when the variable is not set, and `default` alone uses `Default::default()`.
+/// - `secret` shows the field as `[redacted]` in `Debug` and its invalid values in errors.
+/// - `parse_with = "path::to::function"` parses with a function taking `&str` and returning a `Result` with an error implementing `Display`.
+/// - `nested` builds the field from its own `EnvConfig`, with keys prefixed by the field name in uppercase and `_`.
+///   `prefix = "PREFIX_"` sets another prefix.
+///
+/// A field of type `Option` is `None` when the variable is not set, or for a nested field, when no variable has its prefix.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::EnvMap;
+/// use dotenvy_macros::EnvConfig;
+///
+/// #[derive(EnvConfig)]
+/// struct Config {
+///     #[env(default = "8080")]
+///     port: u16,
+///     #[env(key = "DATABASE_URL", secret)]
+///     database: String,
+///     #[env(nested, prefix = "CACHE_")]
+///     cache: Cache,
+///     log_level: Option<String>,
+/// }
+///
+/// #[derive(EnvConfig, Debug)]
+/// struct Cache {
+///     ttl: u64,
+/// }
+///
+/// let mut env_map = EnvMap::new();
+/// env_map.insert("DATABASE_URL".to_owned(), "postgres://localhost".to_owned());
+/// env_map.insert("CACHE_TTL".to_owned(), "60".to_owned());
+///
+/// let config = Config::from_env_map(&env_map)?;
+/// assert_eq!(config.port, 8080);
+/// assert_eq!(config.cache.ttl, 60);
+/// assert_eq!(config.log_level, None);
+/// assert!(!format!("{config:?}").contains("postgres"));
+///
+/// let err = Config::from_env_map(&EnvMap::new()).unwrap_err();
+/// assert_eq!(err.to_string(), "DATABASE_URL is not set; CACHE_TTL is not set");
+/// # Ok::<(), dotenvy::Error>(())
+/// ```
+#[proc_macro_derive(EnvConfig, attributes(env))]
+pub fn derive_env_config(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    env_config::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 struct LoadInput {
     path: String,
     required: bool,
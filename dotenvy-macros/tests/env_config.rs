@@ -0,0 +1,130 @@
+use dotenvy::{EnvMap, Error};
+use dotenvy_macros::EnvConfig;
+use std::{fmt, str::FromStr, time::Duration};
+
+fn env_map(vars: &[(&str, &str)]) -> EnvMap {
+    vars.iter()
+        .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+        .collect()
+}
+
+fn seconds(value: &str) -> Result<Duration, std::num::ParseIntError> {
+    value.parse().map(Duration::from_secs)
+}
+
+#[derive(EnvConfig)]
+struct Db {
+    host: String,
+    #[env(default = "5432")]
+    port: u16,
+    #[env(secret)]
+    password: String,
+}
+
+#[derive(EnvConfig, Debug)]
+struct Config {
+    #[env(key = "APP_NAME")]
+    name: String,
+    #[env(default)]
+    debug: bool,
+    #[env(parse_with = "seconds")]
+    timeout: Duration,
+    workers: Option<u32>,
+    #[env(nested)]
+    db: Db,
+    #[env(nested, prefix = "REPLICA_DB_")]
+    replica: Option<Db>,
+}
+
+#[test]
+fn from_env_map() -> Result<(), Error> {
+    let config = Config::from_env_map(&env_map(&[
+        ("APP_NAME", "app"),
+        ("TIMEOUT", "30"),
+        ("WORKERS", "4"),
+        ("DB_HOST", "localhost"),
+        ("DB_PASSWORD", "hunter2"),
+        ("REPLICA_DB_HOST", "replica"),
+        ("REPLICA_DB_PORT", "5433"),
+        ("REPLICA_DB_PASSWORD", "hunter3"),
+    ]))?;
+    assert_eq!(config.name, "app");
+    assert!(!config.debug);
+    assert_eq!(config.timeout, Duration::from_secs(30));
+    assert_eq!(config.workers, Some(4));
+    assert_eq!(config.db.host, "localhost");
+    assert_eq!(config.db.port, 5432);
+    assert_eq!(config.db.password, "hunter2");
+    let replica = config.replica.as_ref().expect("replica is set");
+    assert_eq!(replica.port, 5433);
+
+    let debug = format!("{config:?}");
+    assert!(debug.contains(r#"host: "localhost""#));
+    assert!(debug.contains("password: [redacted]"));
+    assert!(!debug.contains("hunter"));
+
+    let config = Config::from_env_map(&env_map(&[
+        ("APP_NAME", "app"),
+        ("TIMEOUT", "30"),
+        ("DB_HOST", "localhost"),
+        ("DB_PASSWORD", "hunter2"),
+    ]))?;
+    assert_eq!(config.workers, None);
+    assert!(config.replica.is_none());
+
+    let config = Config::from_env_map(&env_map(&[
+        ("APP_NAME", "app"),
+        ("DEBUG", "yes"),
+        ("TIMEOUT", "30"),
+        ("DB_HOST", "localhost"),
+        ("DB_PASSWORD", "hunter2"),
+    ]))?;
+    assert!(config.debug);
+    Ok(())
+}
+
+#[test]
+fn from_env_map_errors() {
+    let err = Config::from_env_map(&env_map(&[
+        ("DEBUG", "maybe"),
+        ("TIMEOUT", "soon"),
+        ("DB_PORT", "x"),
+        ("DB_PASSWORD", "hunter2"),
+        ("REPLICA_DB_PASSWORD", "not a number"),
+    ]))
+    .unwrap_err();
+    let Error::Multiple(errors) = &err else {
+        panic!("expected Error::Multiple, got {err:?}");
+    };
+    assert_eq!(errors.len(), 6);
+    assert_eq!(
+        err.to_string(),
+        "APP_NAME is not set; \
+         DEBUG has an invalid value 'maybe': expected one of true, false, 1, 0, yes, no, on or off; \
+         TIMEOUT has an invalid value 'soon': invalid digit found in string; \
+         DB_HOST is not set; \
+         DB_PORT has an invalid value 'x': invalid digit found in string; \
+         REPLICA_DB_HOST is not set"
+    );
+}
+
+#[derive(EnvConfig)]
+struct Secret<T: FromStr>
+where
+    T::Err: fmt::Display,
+{
+    #[env(secret)]
+    token: T,
+    user: T,
+}
+
+#[test]
+fn debug_generic() -> Result<(), Error> {
+    let secret = Secret::<String>::from_env_map(&env_map(&[("TOKEN", "hunter2"), ("USER", "me")]))?;
+    assert_eq!(secret.token, "hunter2");
+    assert_eq!(
+        format!("{secret:?}"),
+        r#"Secret { token: [redacted], user: "me" }"#
+    );
+    Ok(())
+}
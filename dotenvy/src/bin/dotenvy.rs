@@ -10,8 +10,14 @@
 //!
 //! will output `bar`.
 use clap::{Parser, Subcommand};
-use dotenvy::{EnvLoader, EnvSequence};
-use std::{error, fs::File, io::ErrorKind, path::PathBuf, process};
+use dotenvy::{EnvLoader, EnvMap, EnvSequence, Error, ValidateOptions};
+use std::{
+    error,
+    fs::File,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process,
+};
 
 fn mk_cmd(program: &str, args: &[String]) -> process::Command {
     let mut cmd = process::Command::new(program);
@@ -21,6 +27,28 @@ fn mk_cmd(program: &str, args: &[String]) -> process::Command {
     cmd
 }
 
+/// Loads the variables and validates them against the example, exiting if there are errors.
+///
+/// Variables from the existing environment count, as the command inherits them.
+fn validate(loader: EnvLoader, example: &Path) -> EnvMap {
+    match loader.validate(example, &ValidateOptions::new()) {
+        Ok((env_map, report)) => {
+            if !report.is_empty() {
+                eprintln!("{report}");
+            }
+            env_map
+        }
+        Err(Error::Validation(report)) => {
+            eprintln!("{report}");
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
+
 #[derive(Parser)]
 #[command(
     name = "dotenvy",
@@ -39,6 +67,9 @@ struct Cli {
     required: bool,
     #[arg(long, default_value_t = false)]
     r#override: bool,
+    #[arg(long)]
+    /// Path to an example env file, such as .env.example, listing every required key
+    example: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -50,7 +81,7 @@ enum Subcmd {
 fn main() -> Result<(), Box<dyn error::Error>> {
     let cli = Cli::parse();
 
-    match File::open(&cli.file) {
+    let env_map = match File::open(&cli.file) {
         Ok(file) => {
             let seq = if cli.r#override {
                 EnvSequence::EnvThenInput
@@ -58,9 +89,12 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                 EnvSequence::InputThenEnv
             };
 
-            // load the file
+            // load the file, validating it against the example if there is one
             let loader = EnvLoader::with_reader(file).path(&cli.file).sequence(seq);
-            unsafe { loader.load_and_modify() }?;
+            match &cli.example {
+                Some(example) => validate(loader, example),
+                None => loader.load()?,
+            }
         }
         Err(e) => {
             if cli.required && e.kind() == ErrorKind::NotFound {
@@ -74,6 +108,7 @@ fn main() -> Result<(), Box<dyn error::Error>> {
     let Subcmd::External(args) = cli.subcmd;
     let (program, args) = args.split_first().unwrap();
     let mut cmd = mk_cmd(program, args);
+    cmd.envs(env_map.iter());
 
     // run the command
     #[cfg(windows)]
@@ -0,0 +1,67 @@
+use crate::{EnvMap, Error};
+use std::fmt::Display;
+
+/// A struct built from variables, usually implemented with `#[derive(EnvConfig)]` from the `macros` feature.
+///
+/// The derive also adds an inherent `from_env_map` constructor, so the trait does not need to be imported,
+/// and a `Debug` implementation that redacts secret fields if there are any. See the derive for the supported attributes.
+pub trait EnvConfig: Sized {
+    /// Builds the struct from the variables whose keys start with the prefix.
+    ///
+    /// Every missing or invalid variable is pushed to `errors`, and `None` is returned if there were any.
+    fn from_env_map_prefixed(
+        env_map: &EnvMap,
+        prefix: &str,
+        errors: &mut Vec<Error>,
+    ) -> Option<Self>;
+
+    /// Builds the struct from the variables.
+    ///
+    /// Fails with `Error::Multiple` listing every missing or invalid variable.
+    fn from_env_map(env_map: &EnvMap) -> Result<Self, Error> {
+        let mut errors = Vec::new();
+        match Self::from_env_map_prefixed(env_map, "", &mut errors) {
+            Some(config) if errors.is_empty() => Ok(config),
+            _ => Err(Error::Multiple(errors)),
+        }
+    }
+}
+
+/// Reads and parses a variable for `#[derive(EnvConfig)]`.
+///
+/// An unset variable without a default is `None`, and pushes `Error::NotPresent` if it is required.
+pub fn read_var<T, E, F>(
+    env_map: &EnvMap,
+    key: &str,
+    default: Option<&str>,
+    required: bool,
+    secret: bool,
+    parse: F,
+    errors: &mut Vec<Error>,
+) -> Option<T>
+where
+    F: FnOnce(&str) -> Result<T, E>,
+    E: Display,
+{
+    let value = match (env_map.get(key), default) {
+        (Some(value), _) => value.as_str(),
+        (None, Some(default)) => default,
+        (None, None) => {
+            if required {
+                errors.push(Error::NotPresent(key.to_owned()));
+            }
+            return None;
+        }
+    };
+    match parse(value) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            errors.push(Error::InvalidValue {
+                key: key.to_owned(),
+                value: if secret { "[redacted]" } else { value }.to_owned(),
+                message: e.to_string(),
+            });
+            None
+        }
+    }
+}
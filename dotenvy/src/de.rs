@@ -0,0 +1,583 @@
+use crate::{typed::parse_bool, EnvMap, Error};
+use serde::{
+    de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor},
+    forward_to_deserialize_any, Deserialize,
+};
+use std::{fmt::Display, str::FromStr, vec};
+
+/// Separates the name of a nested struct from the name of its field, as in `DB__HOST`.
+const SEPARATOR: &str = "__";
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Deserialize(msg.to_string())
+    }
+}
+
+impl EnvMap {
+    /// Deserializes the variables into a type implementing `serde::Deserialize`, such as a config struct.
+    ///
+    /// Requires the `serde` feature. See [`Deserializer`] for how variables map to fields.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::EnvMap;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     port: u16,
+    ///     hosts: Vec<String>,
+    ///     db: Db,
+    ///     log_level: Option<String>,
+    /// }
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Db {
+    ///     url: String,
+    /// }
+    ///
+    /// let mut env_map = EnvMap::new();
+    /// env_map.insert("PORT".to_owned(), "8080".to_owned());
+    /// env_map.insert("HOSTS".to_owned(), "a.com,b.com".to_owned());
+    /// env_map.insert("DB__URL".to_owned(), "postgres://localhost".to_owned());
+    ///
+    /// let config: Config = env_map.deserialize()?;
+    /// assert_eq!(config.port, 8080);
+    /// assert_eq!(config.hosts, ["a.com", "b.com"]);
+    /// assert_eq!(config.db.url, "postgres://localhost");
+    /// assert_eq!(config.log_level, None);
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T, Error> {
+        T::deserialize(Deserializer::new(self))
+    }
+
+    /// Deserializes the variables whose keys start with the prefix, such as `APP_`, with the prefix removed.
+    ///
+    /// Requires the `serde` feature. The prefix is matched ignoring case, and other variables are ignored.
+    pub fn deserialize_prefixed<'de, T: Deserialize<'de>>(
+        &'de self,
+        prefix: &str,
+    ) -> Result<T, Error> {
+        T::deserialize(Deserializer::with_prefix(self, prefix))
+    }
+}
+
+/// A `serde` deserializer over the variables of an [`EnvMap`].
+///
+/// Requires the `serde` feature.
+///
+/// - Struct fields match keys ignoring case, so `port` is read from `PORT`.
+/// - `__` separates nested structs from their fields, so `db.host` is read from `DB__HOST`.
+/// - Sequences are comma-separated, e.g. `a.com, b.com`. Whitespace around each item is trimmed.
+/// - Booleans are parsed like [`EnvMap::parse_bool`], and other values with `FromStr`.
+/// - A missing or empty variable is `None` for an `Option` field. A missing variable is an error otherwise.
+/// - A unit enum variant is read from its name, e.g. `Debug`.
+///
+/// A value that cannot be parsed fails with `Error::InvalidValue`, and other problems such as a missing field with `Error::Deserialize`.
+pub struct Deserializer<'de> {
+    node: Node<'de>,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a deserializer over all of the variables.
+    #[must_use]
+    pub fn new(env_map: &'de EnvMap) -> Self {
+        Self::with_prefix(env_map, "")
+    }
+
+    /// Creates a deserializer over the variables whose keys start with the prefix, with the prefix removed.
+    #[must_use]
+    pub fn with_prefix(env_map: &'de EnvMap, prefix: &str) -> Self {
+        let mut root = Node::default();
+        for (key, value) in env_map {
+            let Some(name) = strip_prefix_ignore_case(key, prefix) else {
+                continue;
+            };
+            if name.is_empty() {
+                continue;
+            }
+            let offset = key.len() - name.len();
+            let mut node = &mut root;
+            let mut end = offset;
+            for segment in name.split(SEPARATOR) {
+                end += segment.len();
+                node = node.child(segment, &key[offset..end]);
+                end += SEPARATOR.len();
+            }
+            node.var = Some(Value { key, value });
+        }
+        Self { node: root }
+    }
+
+    /// Returns the variable at this node, for types that are read from a single value.
+    fn value(&self) -> Result<Value<'de>, Error> {
+        self.node.var.ok_or_else(|| {
+            Error::Deserialize(format!(
+                "expected a value for {}, found only nested variables",
+                self.node.name
+            ))
+        })
+    }
+}
+
+/// A key, or a part of a key split at `__`, with the variables nested under it.
+#[derive(Default)]
+struct Node<'de> {
+    /// The key up to the end of this part, without the prefix.
+    name: &'de str,
+    var: Option<Value<'de>>,
+    children: Vec<(&'de str, Self)>,
+}
+
+impl<'de> Node<'de> {
+    /// Returns the child for the part of a key, matched ignoring case, adding it if needed.
+    fn child(&mut self, segment: &'de str, name: &'de str) -> &mut Self {
+        let i = self
+            .children
+            .iter()
+            .position(|(s, _)| s.eq_ignore_ascii_case(segment))
+            .unwrap_or_else(|| {
+                self.children.push((
+                    segment,
+                    Self {
+                        name,
+                        ..Self::default()
+                    },
+                ));
+                self.children.len() - 1
+            });
+        &mut self.children[i].1
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(key: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = key.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &key[prefix.len()..])
+}
+
+macro_rules! forward_to_value {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            self.value()?.$method(visitor)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.node.var {
+            Some(value) if self.node.children.is_empty() => value.deserialize_any(visitor),
+            _ => self.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(Fields::new(self.node.children))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let mut children = self.node.children;
+        let fields = fields
+            .iter()
+            .filter_map(|&field| {
+                let i = children
+                    .iter()
+                    .position(|(segment, _)| segment.eq_ignore_ascii_case(field))?;
+                Some((field, children.swap_remove(i).1))
+            })
+            .collect();
+        visitor.visit_map(Fields::new(fields))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.node.var {
+            // an empty value counts as unset, as when validating
+            Some(value) if value.value.is_empty() && self.node.children.is_empty() => {
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.value()?.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value()?.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_unit deserialize_seq deserialize_identifier
+    }
+}
+
+/// The fields of a struct or the entries of a map, with their nodes.
+struct Fields<'de> {
+    fields: vec::IntoIter<(&'de str, Node<'de>)>,
+    node: Option<Node<'de>>,
+}
+
+impl<'de> Fields<'de> {
+    fn new(fields: Vec<(&'de str, Node<'de>)>) -> Self {
+        Self {
+            fields: fields.into_iter(),
+            node: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for Fields<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((name, node)) = self.fields.next() else {
+            return Ok(None);
+        };
+        self.node = Some(node);
+        seed.deserialize(name.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let node = self
+            .node
+            .take()
+            .ok_or_else(|| Error::Deserialize("value requested before key".to_owned()))?;
+        // Errors for a single value, such as an unknown enum variant, are reported with the variable.
+        let var = node.var.filter(|_| node.children.is_empty());
+        seed.deserialize(Deserializer { node })
+            .map_err(|e| match (e, var) {
+                (Error::Deserialize(message), Some(var)) => var.invalid(message),
+                (e, _) => e,
+            })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+/// The value of a single variable.
+#[derive(Clone, Copy)]
+struct Value<'de> {
+    key: &'de str,
+    value: &'de str,
+}
+
+impl Value<'_> {
+    fn parse<T>(self) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.value.parse().map_err(|e| self.invalid(e))
+    }
+
+    fn invalid<M: Display>(self, message: M) -> Error {
+        Error::InvalidValue {
+            key: self.key.to_owned(),
+            value: self.value.to_owned(),
+            message: message.to_string(),
+        }
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.$visit(self.parse()?)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Value<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.value)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bool(parse_bool(self.value).map_err(|message| self.invalid(message))?)
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8
+        deserialize_i16 => visit_i16
+        deserialize_i32 => visit_i32
+        deserialize_i64 => visit_i64
+        deserialize_i128 => visit_i128
+        deserialize_u8 => visit_u8
+        deserialize_u16 => visit_u16
+        deserialize_u32 => visit_u32
+        deserialize_u64 => visit_u64
+        deserialize_u128 => visit_u128
+        deserialize_f32 => visit_f32
+        deserialize_f64 => visit_f64
+        deserialize_char => visit_char
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let items = if self.value.trim().is_empty() {
+            Vec::new()
+        } else {
+            self.value.split(',').map(str::trim).collect()
+        };
+        visitor.visit_seq(Items {
+            value: self,
+            items: items.into_iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// The comma-separated items of a value.
+struct Items<'de> {
+    value: Value<'de>,
+    items: vec::IntoIter<&'de str>,
+}
+
+impl<'de> SeqAccess<'de> for Items<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some(item) = self.items.next() else {
+            return Ok(None);
+        };
+        let value = Value {
+            key: self.value.key,
+            value: item,
+        };
+        seed.deserialize(value).map(Some).map_err(|e| match e {
+            Error::InvalidValue { message, .. } | Error::Deserialize(message) => self
+                .value
+                .invalid(format!("invalid item '{item}': {message}")),
+            e => e,
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EnvMap, Error};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    fn env_map(vars: &[(&str, &str)]) -> EnvMap {
+        vars.iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect()
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Debug,
+        Info,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    struct Db {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    struct Config {
+        name: String,
+        debug: bool,
+        ports: Vec<u16>,
+        level: Level,
+        db: Db,
+        timeout: Option<u32>,
+        max_connections: Option<u32>,
+    }
+
+    #[test]
+    fn deserialize() -> Result<(), Error> {
+        let env_map = env_map(&[
+            ("NAME", "app"),
+            ("Debug", "yes"),
+            ("PORTS", "80, 443"),
+            ("LEVEL", "info"),
+            ("DB__HOST", "localhost"),
+            ("db__Port", "5432"),
+            ("MAX_CONNECTIONS", "10"),
+            ("UNRELATED", "x"),
+        ]);
+        let config: Config = env_map.deserialize()?;
+        assert_eq!(
+            config,
+            Config {
+                name: "app".to_owned(),
+                debug: true,
+                ports: vec![80, 443],
+                level: Level::Info,
+                db: Db {
+                    host: "localhost".to_owned(),
+                    port: 5432,
+                },
+                timeout: None,
+                max_connections: Some(10),
+            }
+        );
+
+        let db: HashMap<String, String> = env_map.deserialize_prefixed("DB__")?;
+        assert_eq!(db.len(), 2);
+        assert_eq!(db["HOST"], "localhost");
+
+        // an empty value is `None` rather than an invalid number
+        let mut env_map = env_map;
+        env_map.insert("TIMEOUT".to_owned(), String::new());
+        env_map.insert("MAX_CONNECTIONS".to_owned(), String::new());
+        let config: Config = env_map.deserialize()?;
+        assert_eq!((config.timeout, config.max_connections), (None, None));
+        let limits: HashMap<String, Option<u32>> = env_map.deserialize_prefixed("MAX_")?;
+        assert_eq!(limits["CONNECTIONS"], None);
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_prefixed() -> Result<(), Error> {
+        let env_map = env_map(&[
+            ("APP_HOST", "localhost"),
+            ("app_port", "5432"),
+            ("PORT", "80"),
+        ]);
+        let db: Db = env_map.deserialize_prefixed("APP_")?;
+        assert_eq!(db.host, "localhost");
+        assert_eq!(db.port, 5432);
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        let err = env_map(&[("HOST", "localhost"), ("PORT", "http")])
+            .deserialize::<Db>()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "PORT has an invalid value 'http': invalid digit found in string"
+        );
+
+        let err = env_map(&[("HOST", "localhost")])
+            .deserialize::<Db>()
+            .unwrap_err();
+        assert!(matches!(err, Error::Deserialize(message) if message == "missing field `port`"));
+
+        let err = env_map(&[
+            ("NAME", "app"),
+            ("DEBUG", "0"),
+            ("PORTS", "80,x"),
+            ("LEVEL", "info"),
+        ])
+        .deserialize::<Config>()
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "PORTS has an invalid value '80,x': invalid item 'x': invalid digit found in string"
+        );
+
+        let err = env_map(&[("LEVEL", "trace")])
+            .deserialize::<HashMap<String, Level>>()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidValue { key, value, .. } if key == "LEVEL" && value == "trace"
+        ));
+    }
+}
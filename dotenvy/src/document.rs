@@ -0,0 +1,592 @@
+use crate::{
+    iter::{Lines, ParseBufError, RawLine},
+    parse, quote, ParseErrorKind, Position,
+};
+use std::fmt;
+
+/// A lossless syntax tree of the contents of an env file.
+///
+/// Comments, blank lines, quoting, whitespace and line endings are kept as they are,
+/// so that formatting a parsed document reproduces the input byte for byte.
+/// Lines are split and parsed with the same rules as [`EnvLoader`](crate::EnvLoader).
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::Document;
+///
+/// let input = "# database\nexport DB_HOST='localhost' # local\n\nDB_PORT=5432\n";
+/// let mut doc = Document::parse(input);
+/// assert_eq!(doc.to_string(), input);
+///
+/// let entry = doc.get_mut("DB_HOST").unwrap();
+/// assert_eq!(entry.value()?, "localhost");
+/// entry.set_raw_value("\"db.internal\"")?;
+/// assert_eq!(
+///     doc.to_string(),
+///     "# database\nexport DB_HOST=\"db.internal\" # local\n\nDB_PORT=5432\n"
+/// );
+/// # Ok::<(), dotenvy::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Document {
+    /// Whether the input starts with a byte order mark.
+    bom: bool,
+    nodes: Vec<Node>,
+}
+
+/// A logical line of a [`Document`], including its line ending.
+///
+/// A logical line spans several physical lines when a quoted value contains line breaks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    /// A line containing only whitespace.
+    Blank(String),
+    /// A line containing only a comment, with its leading whitespace.
+    Comment(String),
+    /// A variable definition.
+    Entry(EntryNode),
+    /// A line that `EnvLoader` would fail to parse, kept verbatim.
+    Invalid(String),
+}
+
+/// A variable definition in a [`Document`].
+///
+/// The definition is split into the text before the key, such as indentation and `export`,
+/// the key, the text between the key and the value, the value as written, and the text after the value,
+/// such as a trailing comment and the line ending.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryNode {
+    prefix: String,
+    key: String,
+    separator: String,
+    raw_value: String,
+    suffix: String,
+}
+
+impl Document {
+    /// Parses the contents of an env file.
+    ///
+    /// This is infallible. Lines that cannot be parsed are kept as [`Node::Invalid`].
+    #[must_use]
+    pub fn parse(input: &str) -> Self {
+        let (bom, input) = input
+            .strip_prefix('\u{feff}')
+            .map_or((false, input), |input| (true, input));
+        let mut lines = Lines::new(input.as_bytes());
+        let mut nodes = Vec::new();
+        loop {
+            let line_number = lines.line() + 1;
+            // reading from a `str` cannot fail
+            let Some(Ok(raw)) = lines.next_raw() else {
+                break;
+            };
+            nodes.push(Node::from_raw_line(raw, line_number));
+        }
+        Self { bom, nodes }
+    }
+
+    #[must_use]
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Returns the nodes for editing.
+    ///
+    /// Nodes are written in order with no separator, so each node other than the last should end with a line break.
+    pub const fn nodes_mut(&mut self) -> &mut Vec<Node> {
+        &mut self.nodes
+    }
+
+    /// Returns the variable definitions in the order they appear.
+    pub fn entries(&self) -> impl Iterator<Item = &EntryNode> {
+        self.nodes.iter().filter_map(Node::as_entry)
+    }
+
+    /// Returns the last definition of the key, which is the one `EnvLoader` uses.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&EntryNode> {
+        self.nodes
+            .iter()
+            .rev()
+            .filter_map(Node::as_entry)
+            .find(|entry| entry.key == key)
+    }
+
+    /// Returns the last definition of the key for editing.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut EntryNode> {
+        self.nodes
+            .iter_mut()
+            .rev()
+            .filter_map(Node::as_entry_mut)
+            .find(|entry| entry.key == key)
+    }
+
+    /// Sets the value of the key, quoting and escaping it as needed.
+    ///
+    /// The last definition of the key is updated in place, keeping its comment and formatting.
+    /// If the key is not defined, a definition is appended.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), crate::Error> {
+        if let Some(entry) = self.get_mut(key) {
+            return entry.set_value(value);
+        }
+        let entry = EntryNode::new(key, value)?;
+        self.insert_node(self.nodes.len(), Node::Entry(entry));
+        Ok(())
+    }
+
+    /// Removes every definition of the key, returning whether it was defined.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let len = self.nodes.len();
+        self.nodes
+            .retain(|node| !node.as_entry().is_some_and(|entry| entry.key == key));
+        self.nodes.len() != len
+    }
+
+    /// Renames every definition of the key, returning whether it was defined.
+    ///
+    /// Definitions of the new key are removed, so that the renamed value replaces them.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<bool, crate::Error> {
+        // fails if the new key is not valid
+        EntryNode::new(to, "")?;
+        if self.get(from).is_none() {
+            return Ok(false);
+        }
+        if from != to {
+            self.remove(to);
+            for node in &mut self.nodes {
+                if let Node::Entry(entry) = node {
+                    if entry.key == from {
+                        entry.set_key(to)?;
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Inserts a definition of the key after the last definition of `after`, quoting and escaping the value as needed.
+    ///
+    /// If the key is already defined, its last definition is moved, keeping its comment and formatting,
+    /// and other definitions are removed.
+    ///
+    /// Fails with `Error::NotPresent` if `after` is not defined.
+    pub fn insert_after(
+        &mut self,
+        after: &str,
+        key: &str,
+        value: &str,
+    ) -> Result<(), crate::Error> {
+        if self.get(after).is_none() {
+            return Err(crate::Error::NotPresent(after.to_owned()));
+        }
+        if key == after {
+            return self.set(key, value);
+        }
+        let mut entry = match self.get(key) {
+            Some(entry) => entry.clone(),
+            None => EntryNode::new(key, "")?,
+        };
+        entry.set_value(value)?;
+        self.remove(key);
+
+        let index = self
+            .nodes
+            .iter()
+            .rposition(|node| node.as_entry().is_some_and(|entry| entry.key == after))
+            .map_or(self.nodes.len(), |index| index + 1);
+        self.insert_node(index, Node::Entry(entry));
+        Ok(())
+    }
+
+    /// Inserts a node, adding line endings so that it is on a line of its own.
+    fn insert_node(&mut self, index: usize, mut node: Node) {
+        let ending = self.line_ending();
+        if let Some(previous) = index.checked_sub(1).and_then(|i| self.nodes.get_mut(i)) {
+            previous.push_line_ending(ending);
+        }
+        node.push_line_ending(ending);
+        self.nodes.insert(index, node);
+    }
+
+    /// Returns the line ending of the first line, or `\n` if there is none.
+    fn line_ending(&self) -> &'static str {
+        self.nodes
+            .iter()
+            .find_map(Node::line_ending)
+            .unwrap_or("\n")
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.bom {
+            f.write_str("\u{feff}")?;
+        }
+        for node in &self.nodes {
+            node.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Node {
+    fn from_raw_line(raw: RawLine, line_number: usize) -> Self {
+        match EntryNode::from_raw_line(&raw, line_number) {
+            Ok(Some(entry)) => Self::Entry(entry),
+            Ok(None) if raw.text.trim().is_empty() => Self::Blank(raw.text),
+            Ok(None) => Self::Comment(raw.text),
+            Err(_) => Self::Invalid(raw.text),
+        }
+    }
+
+    #[must_use]
+    pub const fn as_entry(&self) -> Option<&EntryNode> {
+        match self {
+            Self::Entry(entry) => Some(entry),
+            Self::Blank(_) | Self::Comment(_) | Self::Invalid(_) => None,
+        }
+    }
+
+    pub const fn as_entry_mut(&mut self) -> Option<&mut EntryNode> {
+        match self {
+            Self::Entry(entry) => Some(entry),
+            Self::Blank(_) | Self::Comment(_) | Self::Invalid(_) => None,
+        }
+    }
+
+    fn line_ending(&self) -> Option<&'static str> {
+        match self {
+            Self::Blank(text) | Self::Comment(text) | Self::Invalid(text) => line_ending(text),
+            Self::Entry(entry) => line_ending(&entry.suffix),
+        }
+    }
+
+    /// Adds the line ending unless the node already ends with one.
+    fn push_line_ending(&mut self, ending: &str) {
+        if self.line_ending().is_some() {
+            return;
+        }
+        match self {
+            Self::Blank(text) | Self::Comment(text) | Self::Invalid(text) => text.push_str(ending),
+            Self::Entry(entry) => entry.suffix.push_str(ending),
+        }
+    }
+}
+
+fn line_ending(text: &str) -> Option<&'static str> {
+    if text.ends_with("\r\n") {
+        Some("\r\n")
+    } else if text.ends_with('\n') {
+        Some("\n")
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Blank(text) | Self::Comment(text) | Self::Invalid(text) => f.write_str(text),
+            Self::Entry(entry) => entry.fmt(f),
+        }
+    }
+}
+
+impl EntryNode {
+    fn from_raw_line(raw: &RawLine, line_number: usize) -> Result<Option<Self>, ParseBufError> {
+        let content = raw.content();
+        let Some(entry) = parse::parse_entry(content, line_number)? else {
+            return Ok(None);
+        };
+        parse::check_value(entry.value, entry.value_position)?;
+
+        let key_end = entry.key_offset + entry.key.len();
+        let value_end = entry.value_offset + entry.value.len();
+        Ok(Some(Self {
+            prefix: content[..entry.key_offset].to_owned(),
+            separator: content[key_end..entry.value_offset].to_owned(),
+            raw_value: entry.value.to_owned(),
+            suffix: raw.text[value_end..].to_owned(),
+            key: entry.key,
+        }))
+    }
+
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the value as written, including quotes and escapes.
+    #[must_use]
+    pub fn raw_value(&self) -> &str {
+        &self.raw_value
+    }
+
+    /// Returns the value with quotes and escapes removed.
+    ///
+    /// Variables are not substituted, so `$NAME` and `${NAME}` are returned as written.
+    pub fn value(&self) -> Result<String, crate::Error> {
+        parse::unquote_value(&self.raw_value, self.value_position()).map_err(|e| (e, None).into())
+    }
+
+    /// Returns whether the definition is prefixed with `export`.
+    #[must_use]
+    pub fn is_exported(&self) -> bool {
+        self.prefix.trim_start().starts_with("export")
+    }
+
+    /// Returns the comment after the value, without the `#`.
+    #[must_use]
+    pub fn comment(&self) -> Option<&str> {
+        self.suffix.trim().strip_prefix('#')
+    }
+
+    /// Replaces the value as written, keeping the rest of the line.
+    ///
+    /// The value must be quoted and escaped as it would be in the file, e.g. `"two words"`.
+    /// If the line would not parse back to this value, an `Error::LineParse` is returned and the entry is left unchanged.
+    /// Its line and column are relative to the start of the entry.
+    pub fn set_raw_value(&mut self, raw_value: &str) -> Result<(), crate::Error> {
+        let entry = self.with_parts(&self.key, raw_value)?;
+        *self = entry;
+        Ok(())
+    }
+
+    /// Replaces the value, quoting and escaping it as needed, and keeping the rest of the line.
+    pub fn set_value(&mut self, value: &str) -> Result<(), crate::Error> {
+        self.set_raw_value(&quote::quote(value))
+    }
+
+    /// Replaces the key, keeping the rest of the line.
+    ///
+    /// If the key is not valid, an `Error::LineParse` is returned and the entry is left unchanged.
+    pub fn set_key(&mut self, key: &str) -> Result<(), crate::Error> {
+        let entry = self.with_parts(key, &self.raw_value)?;
+        *self = entry;
+        Ok(())
+    }
+
+    /// Creates a definition of the key, quoting and escaping the value as needed.
+    ///
+    /// The definition has no line ending, which is added when it is inserted into a [`Document`].
+    pub fn new(key: &str, value: &str) -> Result<Self, crate::Error> {
+        let empty = Self {
+            prefix: String::new(),
+            key: String::new(),
+            separator: "=".to_owned(),
+            raw_value: String::new(),
+            suffix: String::new(),
+        };
+        empty.with_parts(key, &quote::quote(value))
+    }
+
+    /// Returns a copy of the entry with the key and value as written replaced,
+    /// failing unless the line parses back to exactly that key and value.
+    fn with_parts(&self, key: &str, raw_value: &str) -> Result<Self, crate::Error> {
+        let line = format!(
+            "{}{key}{}{raw_value}{}",
+            self.prefix, self.separator, self.suffix
+        );
+        let mut lines = Lines::new(line.as_bytes());
+        let entry = match lines.next_raw() {
+            Some(Ok(raw)) => Self::from_raw_line(&raw, 1).map_err(|e| (e, None))?,
+            _ => None,
+        };
+        let e = match entry {
+            Some(entry)
+                if entry.key == key
+                    && entry.raw_value == raw_value
+                    && lines.next_raw().is_none() =>
+            {
+                return Ok(entry);
+            }
+            // the value ends early, e.g. at a `#` or an unquoted line break
+            Some(entry) if entry.key == key => Position::start_of_line(1)
+                .advance(&self.prefix)
+                .advance(key)
+                .advance(&self.separator)
+                .advance(&entry.raw_value)
+                .err(ParseErrorKind::UnexpectedCharacter),
+            _ => Position::start_of_line(1)
+                .advance(&self.prefix)
+                .err(ParseErrorKind::InvalidKey),
+        };
+        Err((e, None).into())
+    }
+
+    /// The position of the value relative to the start of the entry.
+    fn value_position(&self) -> Position {
+        Position::start_of_line(1)
+            .advance(&self.prefix)
+            .advance(&self.key)
+            .advance(&self.separator)
+    }
+}
+
+impl fmt::Display for EntryNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}{}",
+            self.prefix, self.key, self.separator, self.raw_value, self.suffix
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Document, Node};
+    use crate::{Error, ParseErrorKind};
+
+    #[test]
+    fn round_trip() {
+        let inputs = [
+            "",
+            "\n\n",
+            "KEY=value",
+            "KEY=value\n",
+            "KEY=value\r\nKEY2=value2\r\n",
+            "\u{feff}KEY=value\n",
+            "  export KEY = 'value' # comment  \n",
+            "# comment\n   # indented comment\n\t\n",
+            "KEY=\"multi\nline\r\nvalue\" # comment\nKEY2=${KEY:-default}\n",
+            "KEY='unterminated\nvalue\n",
+            "KEY=a b\n.KEY=value\nKEY value\n=\n",
+            "KEY=#not a comment\nKEY2= # comment\nKEY3\\\n=escaped\n",
+        ];
+        for input in inputs {
+            assert_eq!(Document::parse(input).to_string(), input);
+        }
+    }
+
+    #[test]
+    fn nodes() {
+        let doc =
+            Document::parse("# comment\n\nexport KEY='a b' # c\nKEY2=\"x\ny\"\nKEY3 = z\nBAD 1");
+        let nodes = doc.nodes();
+
+        assert_eq!(nodes.len(), 6);
+        assert_eq!(nodes[0], Node::Comment("# comment\n".to_owned()));
+        assert_eq!(nodes[1], Node::Blank("\n".to_owned()));
+        assert_eq!(nodes[5], Node::Invalid("BAD 1".to_owned()));
+
+        let entries = doc.entries().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].key(), "KEY");
+        assert_eq!(entries[0].raw_value(), "'a b'");
+        assert!(entries[0].is_exported());
+        assert_eq!(entries[0].comment(), Some(" c"));
+        assert_eq!(entries[1].raw_value(), "\"x\ny\"");
+        assert!(!entries[1].is_exported());
+        assert_eq!(entries[1].comment(), None);
+        assert_eq!(entries[2].key(), "KEY3");
+        assert_eq!(entries[2].raw_value(), "z");
+    }
+
+    #[test]
+    fn value() -> Result<(), Error> {
+        let doc = Document::parse("KEY=\"a\\tb $HOME\"\nKEY='x'\nKEY2=\\$HOME");
+        assert_eq!(doc.get("KEY").unwrap().value()?, "x");
+        assert_eq!(doc.get("KEY2").unwrap().value()?, "$HOME");
+        assert_eq!(doc.entries().next().unwrap().value()?, "a\tb $HOME");
+        assert!(doc.get("KEY3").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn set_raw_value() -> Result<(), Error> {
+        let mut doc = Document::parse("A=1\n  B = 2 # two\r\nC=3");
+        doc.get_mut("B").unwrap().set_raw_value("'two words'")?;
+        doc.get_mut("C").unwrap().set_raw_value("\"multi\nline\"")?;
+        assert_eq!(
+            doc.to_string(),
+            "A=1\n  B = 'two words' # two\r\nC=\"multi\nline\""
+        );
+        assert_eq!(doc.get("C").unwrap().value()?, "multi\nline");
+        Ok(())
+    }
+
+    #[test]
+    fn edit() -> Result<(), Error> {
+        let mut doc = Document::parse("# config\r\nA=1 # one\r\nB=2\r\nA=3\r\nC=4");
+        doc.set("A", "x y")?;
+        doc.set("D", "5")?;
+        assert!(doc.remove("B"));
+        assert!(!doc.remove("B"));
+        assert_eq!(
+            doc.to_string(),
+            "# config\r\nA=1 # one\r\nA='x y'\r\nC=4\r\nD=5\r\n"
+        );
+
+        assert!(doc.rename("A", "B")?);
+        assert!(!doc.rename("Z", "Y")?);
+        assert_eq!(
+            doc.to_string(),
+            "# config\r\nB=1 # one\r\nB='x y'\r\nC=4\r\nD=5\r\n"
+        );
+
+        doc.insert_after("B", "D", "6")?;
+        doc.insert_after("C", "E", "$7")?;
+        assert_eq!(
+            doc.to_string(),
+            "# config\r\nB=1 # one\r\nB='x y'\r\nD=6\r\nC=4\r\nE='$7'\r\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rename_replaces_new_key() -> Result<(), Error> {
+        let mut doc = Document::parse("A=1\nB=2\nexport C=3 # c");
+        assert!(doc.rename("C", "A")?);
+        assert_eq!(doc.to_string(), "B=2\nexport A=3 # c");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_edits() {
+        let mut doc = Document::parse("A=1\n");
+        for key in ["", "1A", "A B", "#A", "A=B"] {
+            assert!(
+                matches!(
+                    doc.set(key, "value"),
+                    Err(Error::LineParse {
+                        kind: ParseErrorKind::InvalidKey | ParseErrorKind::MissingEquals,
+                        ..
+                    })
+                ),
+                "{key}"
+            );
+            assert!(doc.rename("A", key).is_err(), "{key}");
+        }
+        assert!(matches!(
+            doc.insert_after("Z", "B", "2"),
+            Err(Error::NotPresent(ref key)) if key == "Z"
+        ));
+        assert_eq!(doc.to_string(), "A=1\n");
+    }
+
+    #[test]
+    fn set_invalid_raw_value() {
+        let mut doc = Document::parse("A=1 # one\nB=2\n");
+        let cases = [
+            ("two words", ParseErrorKind::UnexpectedCharacter, 7),
+            ("'unterminated", ParseErrorKind::UnterminatedSingleQuote, 3),
+            ("a #b", ParseErrorKind::UnexpectedCharacter, 4),
+            ("x\nC=3", ParseErrorKind::UnexpectedCharacter, 4),
+            ("\\q", ParseErrorKind::InvalidEscape, 4),
+        ];
+        for (raw_value, expected_kind, expected_column) in cases {
+            let result = doc.get_mut("A").unwrap().set_raw_value(raw_value);
+            assert!(
+                matches!(
+                    result,
+                    Err(Error::LineParse { kind, path: None, line: 1, column })
+                        if kind == expected_kind && column == expected_column
+                ),
+                "{raw_value}: {result:?}"
+            );
+        }
+        assert_eq!(doc.to_string(), "A=1 # one\nB=2\n");
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{
+    iter::{Iter, Layer, ParseBufError},
+    ParseErrorKind,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufReader, Read},
+    path::PathBuf,
+    vec,
+};
+
+/// A line and column in the input, both starting at 1.
+///
+/// The column counts characters, not bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub(crate) const fn start_of_line(line: usize) -> Self {
+        Self { line, column: 1 }
+    }
+
+    /// Returns the position after `text`, which starts at this position.
+    pub(crate) fn advance(mut self, text: &str) -> Self {
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self
+    }
+
+    pub(crate) const fn err(self, kind: ParseErrorKind) -> ParseBufError {
+        ParseBufError::LineParse {
+            kind,
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A variable read from the input, with the position at which it is defined.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Entry {
+    /// The name of the variable.
+    pub key: String,
+    /// The value of the variable, after unquoting, unescaping and substitution.
+    pub value: String,
+    /// The position of the key, after an optional `export`.
+    pub position: Position,
+    /// The position at which the value starts, e.g. its opening quote.
+    pub value_position: Position,
+    /// The path of the input the entry was read from, if it has one.
+    pub path: Option<PathBuf>,
+}
+
+/// An iterator over the entries of the input, in the order they are defined.
+///
+/// This is created by [`EnvLoader::entries`](crate::EnvLoader::entries).
+pub struct Entries<'a> {
+    inner: Inner<'a>,
+    /// The path of the input being read.
+    path: Option<PathBuf>,
+    /// The inputs after the one being read.
+    layers: vec::IntoIter<Layer<'a>>,
+    /// The values yielded so far, which are seen by substitution in later inputs.
+    values: HashMap<String, String>,
+}
+
+enum Inner<'a> {
+    /// Entries are parsed as the input is read.
+    Streaming(Iter<'a, BufReader<Box<dyn Read + 'a>>>),
+    /// Entries were resolved after reading the entire input, for deferred substitution.
+    Resolved(vec::IntoIter<Result<Entry, ParseBufError>>),
+    /// Reading stopped after an IO error.
+    Done,
+}
+
+impl<'a> Entries<'a> {
+    pub(crate) fn new(layers: Vec<Layer<'a>>) -> Result<Self, crate::Error> {
+        let mut entries = Self {
+            inner: Inner::Done,
+            path: None,
+            layers: layers.into_iter(),
+            values: HashMap::new(),
+        };
+        entries.next_layer().transpose()?;
+        Ok(entries)
+    }
+
+    /// Starts reading the next input, returning `None` if there is none left.
+    fn next_layer(&mut self) -> Option<Result<(), crate::Error>> {
+        let Layer { path, iter } = self.layers.next()?;
+        let mut iter = iter.earlier_values(self.values.clone());
+        self.path = path;
+        if let Err(e) = iter.remove_bom() {
+            self.stop();
+            return Some(Err(crate::Error::Io(e, self.path.clone())));
+        }
+        self.inner = if iter.is_deferred() {
+            let resolved = match iter.resolve_all() {
+                Ok(entries) => entries.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            Inner::Resolved(resolved.into_iter())
+        } else {
+            Inner::Streaming(iter)
+        };
+        Some(Ok(()))
+    }
+
+    /// Stops reading, after an IO error.
+    fn stop(&mut self) {
+        self.inner = Inner::Done;
+        self.layers = Vec::new().into_iter();
+    }
+}
+
+impl Iterator for Entries<'_> {
+    type Item = Result<Entry, crate::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = match &mut self.inner {
+                Inner::Streaming(iter) => iter.next_entry(),
+                Inner::Resolved(entries) => entries.next(),
+                Inner::Done => return None,
+            };
+            let Some(item) = item else {
+                match self.next_layer() {
+                    Some(Ok(())) => continue,
+                    Some(Err(e)) => return Some(Err(e)),
+                    None => {
+                        self.inner = Inner::Done;
+                        return None;
+                    }
+                }
+            };
+            return Some(match item {
+                Ok(mut entry) => {
+                    self.values.insert(entry.key.clone(), entry.value.clone());
+                    // entries of included files have the path of the included file
+                    if entry.path.is_none() {
+                        entry.path.clone_from(&self.path);
+                    }
+                    Ok(entry)
+                }
+                Err(e) => {
+                    if matches!(e, ParseBufError::Io(_)) {
+                        self.stop();
+                    }
+                    Err((e, self.path.clone()).into())
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::Document;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// An env file that can be edited in place, keeping its comments, formatting and order.
+///
+/// This is a [`Document`] with the path it is saved to. Values are quoted and escaped as needed,
+/// so that loading the saved file with [`EnvLoader`](crate::EnvLoader) yields exactly the values that were set.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::EnvFile;
+///
+/// let mut file = EnvFile::open(".env")?;
+/// file.set("API_KEY", "new secret")?;
+/// file.rename("DB_URL", "DATABASE_URL")?;
+/// file.insert_after("DATABASE_URL", "DATABASE_POOL_SIZE", "10")?;
+/// file.remove("LEGACY_FLAG");
+/// file.save()?;
+/// # Ok::<(), dotenvy::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvFile {
+    path: PathBuf,
+    document: Document,
+}
+
+impl EnvFile {
+    /// Reads and parses the file at the path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, crate::Error> {
+        let path = path.as_ref().to_owned();
+        let input = fs::read_to_string(&path).map_err(|e| crate::Error::from((e, path.clone())))?;
+        Ok(Self {
+            path,
+            document: Document::parse(&input),
+        })
+    }
+
+    /// Creates an empty file at the path. IO is deferred until `save` is called.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            document: Document::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the file, replacing it atomically.
+    ///
+    /// The contents are written to a temporary file in the same directory, which is then renamed over the file,
+    /// so that readers see either the old or the new contents. The permissions of an existing file are kept.
+    pub fn save(&self) -> Result<(), crate::Error> {
+        self.write_atomic()
+            .map_err(|e| crate::Error::from((e, self.path.clone())))
+    }
+
+    fn write_atomic(&self) -> io::Result<()> {
+        let file_name = self.path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "path does not name a file")
+        })?;
+        let mut temp_name = file_name.to_owned();
+        temp_name.push(format!(".{}.tmp", process::id()));
+        let temp_path = self.path.with_file_name(temp_name);
+
+        let result = self.write_to(&temp_path);
+        let result = result.and_then(|()| fs::rename(&temp_path, &self.path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    fn write_to(&self, temp_path: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(temp_path)?;
+        match fs::metadata(&self.path) {
+            Ok(metadata) => file.set_permissions(metadata.permissions())?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        file.write_all(self.document.to_string().as_bytes())?;
+        file.sync_all()
+    }
+}
+
+impl Deref for EnvFile {
+    type Target = Document;
+
+    fn deref(&self) -> &Self::Target {
+        &self.document
+    }
+}
+
+impl DerefMut for EnvFile {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.document
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EnvFile;
+    use crate::{test_util::TempDir, EnvLoader, EnvSequence};
+    use std::fs;
+
+    #[test]
+    fn save() -> Result<(), crate::Error> {
+        let dir = TempDir::new("env-file");
+        let path = dir.write(".env", "# app\nHOST=localhost # dev\nPORT=80\n");
+
+        let mut file = EnvFile::open(&path)?;
+        file.set("HOST", "example.com")?;
+        file.set("SECRET", "a 'quoted' $value\\n")?;
+        file.insert_after("HOST", "PORT", "443")?;
+        file.save()?;
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# app\nHOST=example.com # dev\nPORT=443\nSECRET=\"a 'quoted' \\$value\\\\n\"\n"
+        );
+        let env_map = EnvLoader::with_path(&path)
+            .sequence(EnvSequence::InputOnly)
+            .load()?;
+        assert_eq!(env_map.var("SECRET")?, "a 'quoted' $value\\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn open_not_found() {
+        let err = EnvFile::open("does-not-exist.env").unwrap_err();
+        assert!(err.not_found());
+    }
+}
@@ -1,10 +1,18 @@
 use std::{error, ffi::OsString, fmt, io, path::PathBuf};
 
-use crate::iter::ParseBufError;
+use crate::{iter::ParseBufError, Origin, ValidationReport};
 
 #[derive(Debug)]
 pub enum Error {
-    LineParse(String, usize),
+    /// The input could not be parsed.
+    ///
+    /// `line` and `column` start at 1 and point at the problem, e.g. the opening quote of an unterminated value.
+    LineParse {
+        kind: ParseErrorKind,
+        path: Option<PathBuf>,
+        line: usize,
+        column: usize,
+    },
     /// An IO error may be encountered when reading from a file or reader.
     Io(io::Error, Option<PathBuf>),
     /// The variable was not found in the environment. The `String` is the name of the variable.
@@ -19,6 +27,51 @@ pub enum Error {
     ///
     /// Only `EnvLoader::default` would have no path or reader.
     NoInput,
+    /// A variable referenced with `${NAME:?message}` or `${NAME?message}` was not set.
+    ///
+    /// `line` is the line of the input on which the variable was referenced.
+    Required {
+        name: String,
+        message: String,
+        path: Option<PathBuf>,
+        line: usize,
+    },
+    /// Variables reference each other in a cycle. The `Vec` is the path of the cycle, starting and ending with the same key.
+    ///
+    /// This is only detected when substitution is deferred with `EnvLoader::deferred_substitution`.
+    SubstitutionCycle(Vec<String>, Option<PathBuf>),
+    /// The value of a variable could not be parsed into the requested type.
+    ///
+    /// `message` is the reason the value is invalid, e.g. the error of `FromStr`.
+    InvalidValue {
+        key: String,
+        value: String,
+        message: String,
+    },
+    /// An error in a file included by an `#include` or `source` directive.
+    ///
+    /// `chain` is the location of each directive through which the file was included, innermost first.
+    /// The path of `error` is the path of the included file.
+    Include {
+        error: Box<Self>,
+        chain: Vec<Origin>,
+    },
+    /// Variables could not be deserialized with the `serde` feature, e.g. because a field is missing.
+    ///
+    /// Invalid values are reported as `InvalidValue` instead.
+    Deserialize(String),
+    /// Several errors reported at once, e.g. every missing or invalid variable of an `EnvConfig`.
+    Multiple(Vec<Self>),
+    /// Validation against an example file found errors. The report also holds any warnings.
+    Validation(ValidationReport),
+    /// An annotation of a schema is invalid.
+    ///
+    /// `line` is the line of the annotation.
+    Schema {
+        message: String,
+        path: Option<PathBuf>,
+        line: usize,
+    },
 }
 
 impl Error {
@@ -36,11 +89,19 @@ impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
             Self::Io(e, _) => Some(e),
-            Self::LineParse(_, _)
+            Self::Include { error, .. } => Some(error),
+            Self::LineParse { .. }
             | Self::NotPresent(_)
             | Self::NotUnicode(_, _)
             | Self::InvalidOp
-            | Self::NoInput => None,
+            | Self::NoInput
+            | Self::Required { .. }
+            | Self::SubstitutionCycle(_, _)
+            | Self::InvalidValue { .. }
+            | Self::Deserialize(_)
+            | Self::Multiple(_)
+            | Self::Validation(_)
+            | Self::Schema { .. } => None,
         }
     }
 }
@@ -55,20 +116,136 @@ impl fmt::Display for Error {
                     e.fmt(f)
                 }
             }
-            Self::LineParse(line, index) => write!(
-                f,
-                "error parsing line: '{line}', error at line index: {index}",
-            ),
+            Self::LineParse {
+                kind,
+                path,
+                line,
+                column,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}:{line}:{column}: {kind}", path.display())
+                } else {
+                    write!(f, "line {line}, column {column}: {kind}")
+                }
+            }
             Self::NotPresent(s) => write!(f, "{s} is not set"),
             Self::NotUnicode(os_str, s) => {
-                write!(f, "{s} is not valid Unicode: {os_str:?}",)
+                write!(f, "{s} is not valid Unicode: {os_str:?}")
             }
             Self::InvalidOp => write!(f, "modify is not permitted with `EnvSequence::EnvOnly`"),
             Self::NoInput => write!(f, "no input provided"),
+            Self::Required {
+                name,
+                message,
+                path,
+                line,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}:{line}: {name}: {message}", path.display())
+                } else {
+                    write!(f, "line {line}: {name}: {message}")
+                }
+            }
+            Self::SubstitutionCycle(cycle, path) => {
+                let cycle = cycle.join(" -> ");
+                if let Some(path) = path {
+                    write!(f, "substitution cycle in '{}': {cycle}", path.display())
+                } else {
+                    write!(f, "substitution cycle: {cycle}")
+                }
+            }
+            Self::InvalidValue {
+                key,
+                value,
+                message,
+            } => write!(f, "{key} has an invalid value '{value}': {message}"),
+            Self::Deserialize(message) => f.write_str(message),
+            Self::Schema {
+                message,
+                path,
+                line,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}:{line}: {message}", path.display())
+                } else {
+                    write!(f, "line {line}: {message}")
+                }
+            }
+            Self::Validation(report) => {
+                for (i, issue) in report.errors().enumerate() {
+                    if i > 0 {
+                        f.write_str("; ")?;
+                    }
+                    write!(f, "{issue}")?;
+                }
+                Ok(())
+            }
+            Self::Multiple(errors) => {
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("; ")?;
+                    }
+                    write!(f, "{e}")?;
+                }
+                Ok(())
+            }
+            Self::Include { error, chain } => {
+                write!(f, "{error}")?;
+                for (i, origin) in chain.iter().enumerate() {
+                    let separator = if i == 0 { " (" } else { ", " };
+                    write!(f, "{separator}included from {origin}")?;
+                }
+                if !chain.is_empty() {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
+/// The kind of problem found when parsing the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A key does not start with an ASCII letter or `_`.
+    InvalidKey,
+    /// A key is not followed by `=`.
+    MissingEquals,
+    /// A `'` is not closed.
+    UnterminatedSingleQuote,
+    /// A `"` is not closed.
+    UnterminatedDoubleQuote,
+    /// A backslash is followed by a character that cannot be escaped, or a `\x` or `\u` escape is malformed.
+    InvalidEscape,
+    /// A `${` is not closed by `}`.
+    UnterminatedSubstitution,
+    /// A `${NAME:` is not followed by a supported operator.
+    InvalidSubstitution,
+    /// An unquoted value contains whitespace followed by something other than a comment.
+    UnexpectedCharacter,
+    /// An include directive includes a file that is already being included.
+    IncludeCycle,
+    /// Include directives are nested too deeply.
+    IncludeDepth,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidKey => "invalid key",
+            Self::MissingEquals => "expected '='",
+            Self::UnterminatedSingleQuote => "unterminated single quote",
+            Self::UnterminatedDoubleQuote => "unterminated double quote",
+            Self::InvalidEscape => "invalid escape sequence",
+            Self::UnterminatedSubstitution => "unterminated substitution",
+            Self::InvalidSubstitution => "invalid substitution",
+            Self::UnexpectedCharacter => "unexpected character",
+            Self::IncludeCycle => "include cycle",
+            Self::IncludeDepth => "includes nested too deeply",
+        })
+    }
+}
+
 impl From<(io::Error, PathBuf)> for Error {
     fn from((e, path): (io::Error, PathBuf)) -> Self {
         Self::Io(e, Some(path))
@@ -77,8 +254,40 @@ impl From<(io::Error, PathBuf)> for Error {
 impl From<(ParseBufError, Option<PathBuf>)> for Error {
     fn from((e, path): (ParseBufError, Option<PathBuf>)) -> Self {
         match e {
-            ParseBufError::LineParse(line, index) => Self::LineParse(line, index),
+            ParseBufError::LineParse { kind, line, column } => Self::LineParse {
+                kind,
+                path,
+                line,
+                column,
+            },
             ParseBufError::Io(e) => Self::Io(e, path),
+            ParseBufError::Required {
+                name,
+                message,
+                line,
+            } => Self::Required {
+                name,
+                message,
+                path,
+                line,
+            },
+            ParseBufError::SubstitutionCycle(cycle) => Self::SubstitutionCycle(cycle, path),
+            ParseBufError::Include { includes, error } => {
+                let mut including = path;
+                let mut chain = Vec::with_capacity(includes.len());
+                for (line, included) in includes {
+                    chain.push(Origin::Input {
+                        path: including,
+                        line,
+                    });
+                    including = Some(included);
+                }
+                chain.reverse();
+                Self::Include {
+                    error: Box::new((*error, including).into()),
+                    chain,
+                }
+            }
         }
     }
 }
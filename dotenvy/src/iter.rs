@@ -1,36 +1,204 @@
-use crate::{parse, EnvMap};
+use crate::{
+    parse::{self, Resolver},
+    Entry, EnvMap, ParseErrorKind, Position, ProcessEnv, SubstitutionSource,
+};
 use std::{
     collections::HashMap,
     env::{self},
-    io::{self, BufRead},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
 };
 
-pub struct Iter<B> {
+/// The maximum number of nested includes.
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// The directives through which a line was included, outermost first.
+///
+/// Each is the line of the directive in the including file and the path of the included file.
+pub type Includes = Vec<(usize, PathBuf)>;
+
+/// An input of a loader, with its path for error context.
+pub struct Layer<'a> {
+    pub path: Option<PathBuf>,
+    pub iter: Iter<'a, BufReader<Box<dyn Read + 'a>>>,
+}
+
+pub struct Iter<'a, B> {
     lines: Lines<B>,
-    substitution_data: HashMap<String, Option<String>>,
+    substitution_data: HashMap<String, String>,
+    substitution_source: Box<dyn SubstitutionSource + 'a>,
+    substitution: bool,
+    deferred_substitution: bool,
+    /// The path of the input, which included paths are relative to.
+    path: Option<PathBuf>,
+    includes: bool,
+    /// The files being included, innermost last.
+    included: Vec<Included>,
+}
+
+/// A file being read because of an include directive.
+struct Included {
+    /// The line of the directive in the including file.
+    line: usize,
+    path: PathBuf,
+    /// The canonical path, for detecting cycles.
+    canonical: PathBuf,
+    lines: Lines<BufReader<File>>,
 }
 
-impl<B: BufRead> Iter<B> {
+impl<'a, B: BufRead> Iter<'a, B> {
     pub fn new(buf: B) -> Self {
         Self {
-            lines: Lines(buf),
+            lines: Lines::new(buf),
             substitution_data: HashMap::new(),
+            substitution_source: Box::new(ProcessEnv),
+            substitution: true,
+            deferred_substitution: false,
+            path: None,
+            includes: false,
+            included: Vec::new(),
         }
     }
 
+    /// Sets the path of the input, which included paths are relative to.
+    pub fn path(mut self, path: Option<PathBuf>) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Enables `#include path` and `source path` directives.
+    pub const fn includes(mut self, enabled: bool) -> Self {
+        self.includes = enabled;
+        self
+    }
+
+    pub fn substitution_source(mut self, source: Box<dyn SubstitutionSource + 'a>) -> Self {
+        self.substitution_source = source;
+        self
+    }
+
+    pub const fn substitution(mut self, enabled: bool) -> Self {
+        self.substitution = enabled;
+        self
+    }
+
+    pub const fn deferred_substitution(mut self, enabled: bool) -> Self {
+        self.deferred_substitution = enabled;
+        self
+    }
+
+    /// Sets the values defined by earlier inputs, which are seen by substitution after the substitution source.
+    pub fn earlier_values(mut self, values: HashMap<String, String>) -> Self {
+        self.substitution_data = values;
+        self
+    }
+
+    pub const fn is_deferred(&self) -> bool {
+        self.deferred_substitution
+    }
+
     fn internal_load<F>(mut self, mut load_fn: F) -> Result<EnvMap, ParseBufError>
     where
         F: FnMut(String, String, &mut EnvMap),
     {
         self.remove_bom()?;
         let mut map = EnvMap::new();
-        for item in self {
-            let (k, v) = item?;
-            load_fn(k, v, &mut map);
+        if self.deferred_substitution {
+            for entry in self.resolve_all()? {
+                load_fn(entry.key, entry.value, &mut map);
+            }
+        } else {
+            for item in self {
+                let (k, v) = item?;
+                load_fn(k, v, &mut map);
+            }
         }
         Ok(map)
     }
 
+    /// Reads all unexpanded values before expanding them, so that values may reference keys defined later.
+    ///
+    /// Keys are returned in the order they first appear, with the value of their last definition.
+    pub fn resolve_all(&mut self) -> Result<Vec<Entry>, ParseBufError> {
+        let lines = self.read_lines()?;
+        let mut resolver = Resolver::new(&self.substitution_source, self.substitution)
+            .earlier(&self.substitution_data);
+        let mut keys = Vec::new();
+        for (line_number, line, includes) in lines {
+            let raw =
+                parse::parse_entry(&line, line_number).map_err(|e| include_error(&includes, e))?;
+            if let Some(raw) = raw {
+                let key = raw.key.clone();
+                if resolver.insert(raw, includes) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys.into_iter()
+            .map(|key| resolver.resolve_entry(key))
+            .collect()
+    }
+
+    /// Loads the input, continuing after lines that cannot be parsed.
+    ///
+    /// Returns the entries that could be parsed along with every error found.
+    /// Only IO errors stop loading, as reading cannot continue after them.
+    pub fn load_lenient(mut self) -> Result<(EnvMap, Vec<ParseBufError>), ParseBufError> {
+        self.remove_bom()?;
+        if self.deferred_substitution {
+            return self.resolve_all_lenient();
+        }
+        let mut map = EnvMap::new();
+        let mut errors = Vec::new();
+        for item in self {
+            match item {
+                Ok((k, v)) => {
+                    map.insert(k, v);
+                }
+                Err(e @ ParseBufError::Io(_)) => return Err(e),
+                Err(e) => errors.push(e),
+            }
+        }
+        Ok((map, errors))
+    }
+
+    /// Like `resolve_all`, but continues after errors.
+    fn resolve_all_lenient(&mut self) -> Result<(EnvMap, Vec<ParseBufError>), ParseBufError> {
+        let mut errors = Vec::new();
+        let mut lines = Vec::new();
+        while let Some(line) = self.next_line() {
+            match line {
+                Ok((line_number, line)) => lines.push((line_number, line, self.include_chain())),
+                Err(e @ ParseBufError::Io(_)) => return Err(e),
+                // a file that could not be included
+                Err(e) => errors.push(e),
+            }
+        }
+        let mut resolver = Resolver::new(&self.substitution_source, self.substitution)
+            .earlier(&self.substitution_data);
+        let mut keys = Vec::new();
+        for (line_number, line, includes) in lines {
+            match parse::parse_entry(&line, line_number) {
+                Ok(Some(raw)) => {
+                    let key = raw.key.clone();
+                    if resolver.insert(raw, includes) {
+                        keys.push(key);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => errors.push(include_error(&includes, e)),
+            }
+        }
+        let (entries, resolve_errors) = resolver.resolve_lenient(keys);
+        errors.extend(resolve_errors);
+        let map = entries
+            .into_iter()
+            .map(|entry| (entry.key, entry.value))
+            .collect();
+        Ok((map, errors))
+    }
+
     pub fn load(self) -> Result<EnvMap, ParseBufError> {
         self.internal_load(|k, v: String, map| {
             map.insert(k, v);
@@ -54,19 +222,159 @@ impl<B: BufRead> Iter<B> {
     }
 
     /// Removes the BOM if it exists.
+    pub fn remove_bom(&mut self) -> io::Result<()> {
+        self.lines.remove_bom()
+    }
+
+    /// Reads the remaining lines, with the includes through which each was read.
+    fn read_lines(&mut self) -> Result<Vec<(usize, String, Includes)>, ParseBufError> {
+        let mut lines = Vec::new();
+        while let Some(line) = self.next_line() {
+            let (line_number, line) = line?;
+            lines.push((line_number, line, self.include_chain()));
+        }
+        Ok(lines)
+    }
+
+    /// Reads the next logical line with its line number, following include directives if enabled.
     ///
-    /// For more info, see the [Unicode BOM character](https://www.compart.com/en/unicode/U+FEFF).
-    fn remove_bom(&mut self) -> io::Result<()> {
-        let buf = self.lines.0.fill_buf()?;
+    /// The line is from the innermost included file, if any. Errors in included files are wrapped in `ParseBufError::Include`.
+    fn next_line(&mut self) -> Option<Result<(usize, String), ParseBufError>> {
+        loop {
+            let (line_number, raw) = if let Some(included) = self.included.last_mut() {
+                let line_number = included.lines.line + 1;
+                match included.lines.next_raw() {
+                    Some(Ok(raw)) => (line_number, raw),
+                    Some(Err(e)) => {
+                        let e = self.wrap(e);
+                        // reading cannot continue after an IO error, so the rest of the file is skipped
+                        self.included.pop();
+                        return Some(Err(e));
+                    }
+                    None => {
+                        self.included.pop();
+                        continue;
+                    }
+                }
+            } else {
+                let line_number = self.lines.line + 1;
+                match self.lines.next_raw()? {
+                    Ok(raw) => (line_number, raw),
+                    Err(e) => return Some(Err(e)),
+                }
+            };
+            if self.includes {
+                if let Some(target) = parse::parse_include(&raw.text, raw.content()) {
+                    if let Err(e) = self.include(&target, line_number) {
+                        return Some(Err(e));
+                    }
+                    continue;
+                }
+            }
+            let mut line = raw.text;
+            line.truncate(raw.content_len);
+            return Some(Ok((line_number, line)));
+        }
+    }
 
-        if buf.starts_with(&[0xEF, 0xBB, 0xBF]) {
-            self.lines.0.consume(3);
+    /// Starts reading the file included by a directive on the line of the innermost file.
+    fn include(&mut self, target: &str, line_number: usize) -> Result<(), ParseBufError> {
+        let including = self
+            .included
+            .last()
+            .map(|included| included.path.as_path())
+            .or(self.path.as_deref());
+        let dir = including
+            .and_then(Path::parent)
+            .unwrap_or_else(|| Path::new(""));
+        let path = dir.join(target);
+
+        let directive = Position::start_of_line(line_number);
+        if self.included.len() >= MAX_INCLUDE_DEPTH {
+            return Err(self.wrap(directive.err(ParseErrorKind::IncludeDepth)));
+        }
+        let opened = fs::canonicalize(&path).and_then(|canonical| {
+            File::open(&path).map(|file| (canonical, Lines::new(BufReader::new(file))))
+        });
+        let (canonical, mut lines) = match opened {
+            Ok(opened) => opened,
+            Err(e) => {
+                let mut includes = self.include_chain();
+                includes.push((line_number, path));
+                return Err(include_error(&includes, ParseBufError::Io(e)));
+            }
+        };
+        let top = self
+            .path
+            .as_deref()
+            .and_then(|path| fs::canonicalize(path).ok());
+        let is_cycle = top.as_ref() == Some(&canonical)
+            || self
+                .included
+                .iter()
+                .any(|included| included.canonical == canonical);
+        if is_cycle {
+            return Err(self.wrap(directive.err(ParseErrorKind::IncludeCycle)));
         }
+        if let Err(e) = lines.remove_bom() {
+            let mut includes = self.include_chain();
+            includes.push((line_number, path));
+            return Err(include_error(&includes, ParseBufError::Io(e)));
+        }
+        self.included.push(Included {
+            line: line_number,
+            path,
+            canonical,
+            lines,
+        });
         Ok(())
     }
+
+    /// Returns the directives through which the current line was included.
+    fn include_chain(&self) -> Includes {
+        self.included
+            .iter()
+            .map(|included| (included.line, included.path.clone()))
+            .collect()
+    }
+
+    /// Wraps an error found in the current line in the directives through which it was included.
+    fn wrap(&self, e: ParseBufError) -> ParseBufError {
+        include_error(&self.include_chain(), e)
+    }
+}
+
+/// Wraps an error in the directives through which its line was included, if any.
+pub fn include_error(includes: &Includes, e: ParseBufError) -> ParseBufError {
+    if includes.is_empty() {
+        e
+    } else {
+        ParseBufError::Include {
+            includes: includes.clone(),
+            error: Box::new(e),
+        }
+    }
 }
 
-struct Lines<B>(B);
+/// Splits the input into logical lines, joining lines within quotes.
+pub struct Lines<B> {
+    buf: B,
+    /// The number of physical lines read so far.
+    line: usize,
+}
+
+/// A logical line exactly as read, including comments and line endings.
+pub struct RawLine {
+    pub text: String,
+    /// The length of the text given to the parser, which excludes trailing comments and the line ending.
+    pub content_len: usize,
+}
+
+impl RawLine {
+    pub fn content(&self) -> &str {
+        &self.text[..self.content_len]
+    }
+}
 
 enum ParseState {
     Complete,
@@ -122,55 +430,78 @@ impl ParseState {
     }
 }
 
-impl<B: BufRead> Iterator for Lines<B> {
-    type Item = Result<String, ParseBufError>;
+impl<B: BufRead> Lines<B> {
+    pub const fn new(buf: B) -> Self {
+        Self { buf, line: 0 }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Removes the BOM if it exists.
+    ///
+    /// For more info, see the [Unicode BOM character](https://www.compart.com/en/unicode/U+FEFF).
+    fn remove_bom(&mut self) -> io::Result<()> {
+        let buf = self.buf.fill_buf()?;
+
+        if buf.starts_with(&[0xEF, 0xBB, 0xBF]) {
+            self.buf.consume(3);
+        }
+        Ok(())
+    }
+
+    /// The number of physical lines read so far.
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Reads the next logical line without removing anything from it.
+    pub fn next_raw(&mut self) -> Option<Result<RawLine, ParseBufError>> {
         let mut buf = String::new();
         let mut cur_state = ParseState::Complete;
         let mut buf_pos;
         let mut cur_pos;
         loop {
             buf_pos = buf.len();
-            match self.0.read_line(&mut buf) {
+            match self.buf.read_line(&mut buf) {
                 Ok(0) => {
                     if matches!(cur_state, ParseState::Complete) {
                         return None;
                     }
-                    let len = buf.len();
-                    return Some(Err(ParseBufError::LineParse(buf, len)));
+                    // the parser reports where the unterminated value started
+                    let content_len = buf.len();
+                    return Some(Ok(RawLine {
+                        text: buf,
+                        content_len,
+                    }));
                 }
                 Ok(_n) => {
+                    self.line += 1;
                     // Skip lines which start with a `#` before iteration
                     // This optimizes parsing a bit.
                     if buf.trim_start().starts_with('#') {
-                        return Some(Ok(String::with_capacity(0)));
+                        return Some(Ok(RawLine {
+                            text: buf,
+                            content_len: 0,
+                        }));
                     }
                     let result = cur_state.eval_end(&buf[buf_pos..]);
                     cur_pos = result.0;
                     cur_state = result.1;
 
-                    match cur_state {
-                        ParseState::Complete => {
-                            if buf.ends_with('\n') {
-                                buf.pop();
-                                if buf.ends_with('\r') {
-                                    buf.pop();
-                                }
-                            }
-                            return Some(Ok(buf));
-                        }
+                    let content_len = match cur_state {
+                        ParseState::Complete => buf
+                            .strip_suffix('\n')
+                            .map_or(buf.len(), |b| b.strip_suffix('\r').unwrap_or(b).len()),
                         ParseState::Escape
                         | ParseState::StrongOpen
                         | ParseState::StrongOpenEscape
                         | ParseState::WeakOpen
                         | ParseState::WeakOpenEscape
-                        | ParseState::WhiteSpace => {}
-                        ParseState::Comment => {
-                            buf.truncate(buf_pos + cur_pos);
-                            return Some(Ok(buf));
-                        }
-                    }
+                        | ParseState::WhiteSpace => continue,
+                        ParseState::Comment => buf_pos + cur_pos,
+                    };
+                    return Some(Ok(RawLine {
+                        text: buf,
+                        content_len,
+                    }));
                 }
                 Err(e) => return Some(Err(ParseBufError::Io(e))),
             }
@@ -178,33 +509,77 @@ impl<B: BufRead> Iterator for Lines<B> {
     }
 }
 
-impl<B: BufRead> Iterator for Iter<B> {
-    type Item = Result<(String, String), ParseBufError>;
+impl<B: BufRead> Iterator for Lines<B> {
+    type Item = Result<String, ParseBufError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        self.next_raw().map(|raw| {
+            raw.map(|mut raw| {
+                raw.text.truncate(raw.content_len);
+                raw.text
+            })
+        })
+    }
+}
+
+impl<B: BufRead> Iter<'_, B> {
+    /// Reads the next entry with its position, skipping empty lines and comments.
+    pub fn next_entry(&mut self) -> Option<Result<Entry, ParseBufError>> {
         loop {
-            let line = match self.lines.next() {
-                Some(Ok(line)) => line,
-                Some(Err(e)) => return Some(Err(e)),
-                None => return None,
+            let (line_number, line) = match self.next_line()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
             };
 
-            match parse::parse_line(&line, &mut self.substitution_data) {
-                Ok(Some(res)) => return Some(Ok(res)),
+            match parse::parse_line(
+                &line,
+                line_number,
+                &mut self.substitution_data,
+                &self.substitution_source,
+                self.substitution,
+            ) {
+                Ok(Some(mut entry)) => {
+                    entry.path = self.included.last().map(|included| included.path.clone());
+                    return Some(Ok(entry));
+                }
                 Ok(None) => {}
-                Err(e) => return Some(Err(e)),
+                Err(e) => return Some(Err(self.wrap(e))),
             }
         }
     }
 }
 
+impl<B: BufRead> Iterator for Iter<'_, B> {
+    type Item = Result<(String, String), ParseBufError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry()
+            .map(|entry| entry.map(|entry| (entry.key, entry.value)))
+    }
+}
+
 /// An internal error type
 ///
 /// This is necessary so we can handle IO errors without knowing the path.
 #[derive(Debug)]
 pub enum ParseBufError {
-    LineParse(String, usize),
+    LineParse {
+        kind: ParseErrorKind,
+        line: usize,
+        column: usize,
+    },
     Io(io::Error),
+    Required {
+        name: String,
+        message: String,
+        line: usize,
+    },
+    SubstitutionCycle(Vec<String>),
+    /// An error in a file included by a directive.
+    Include {
+        includes: Includes,
+        error: Box<Self>,
+    },
 }
 
 impl From<io::Error> for ParseBufError {
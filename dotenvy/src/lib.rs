@@ -5,64 +5,148 @@
     unused_unsafe, // until Rust 2024
 )]
 #![deny(clippy::uninlined_format_args, clippy::wildcard_imports)]
+// test inputs such as `${KEY:-default}` look like format arguments
+#![cfg_attr(test, allow(clippy::literal_string_with_formatting_args))]
 
 //! [`dotenv`]: https://crates.io/crates/dotenv
 //! A well-maintained fork of the [`dotenv`] crate.
 //!
 //! This library allows for loading environment variables from an env file or a reader.
-use crate::iter::Iter;
+use crate::iter::{Iter, Layer, ParseBufError};
 use std::{
     collections::HashMap,
     env::{self, VarError},
+    fmt,
     fs::File,
     io::{BufReader, Read},
-    ops::{Deref, DerefMut},
+    ops::Deref,
     path::{Path, PathBuf},
+    rc::Rc,
+    slice, vec,
 };
 
+mod config;
+#[cfg(feature = "serde")]
+mod de;
+mod document;
+mod entry;
+mod env_file;
 mod err;
 mod iter;
 mod parse;
+mod provenance;
+mod quote;
+#[cfg(feature = "schema")]
+mod schema;
+mod search;
+mod substitution;
+#[cfg(test)]
+mod test_util;
+mod typed;
+mod validate;
+mod write;
 
-/// A map of environment variables.
+/// A map of environment variables that remembers the order in which they were inserted.
+///
+/// This dereferences to `HashMap<String, String>` for lookups, with one additional function, `var`.
+/// Iterating with `iter`, `keys`, `values` or `into_iter` yields variables in insertion order,
+/// so variables loaded from input are in the order they first appear.
+/// Inserting a key that is already present replaces its value but keeps its position.
 ///
-/// This is a newtype around `HashMap<String, String>` with one additional function, `var`.
-#[derive(Default, Clone, Debug, PartialEq, Eq)]
-pub struct EnvMap(HashMap<String, String>);
+/// Two maps are equal if they contain the same variables, regardless of order.
+#[derive(Default, Clone)]
+pub struct EnvMap {
+    map: HashMap<String, String>,
+    /// The keys of `map` in insertion order.
+    order: Vec<String>,
+}
 
 impl Deref for EnvMap {
     type Target = HashMap<String, String>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.map
     }
 }
 
-impl DerefMut for EnvMap {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl PartialEq for EnvMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl Eq for EnvMap {}
+
+impl fmt::Debug for EnvMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
     }
 }
 
 impl FromIterator<(String, String)> for EnvMap {
     fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
-        Self(HashMap::from_iter(iter))
+        let mut env_map = Self::new();
+        env_map.extend(iter);
+        env_map
+    }
+}
+
+impl Extend<(String, String)> for EnvMap {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
     }
 }
 
 impl IntoIterator for EnvMap {
     type Item = (String, String);
-    type IntoIter = std::collections::hash_map::IntoIter<String, String>;
+    type IntoIter = vec::IntoIter<(String, String)>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.order
+            .into_iter()
+            .map(|k| {
+                let v = self.map.remove(&k).unwrap_or_default();
+                (k, v)
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a EnvMap {
+    type Item = (&'a String, &'a String);
+    type IntoIter = EnvMapIter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.iter()
+    }
+}
+
+/// An iterator over the variables of an [`EnvMap`] in insertion order.
+#[derive(Clone, Debug)]
+pub struct EnvMapIter<'a> {
+    keys: slice::Iter<'a, String>,
+    map: &'a HashMap<String, String>,
+}
+
+impl<'a> Iterator for EnvMapIter<'a> {
+    type Item = (&'a String, &'a String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.keys.next().and_then(|k| self.map.get_key_value(k))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
     }
 }
 
 impl EnvMap {
     #[must_use]
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
     }
 
     pub fn var(&self, key: &str) -> Result<String, crate::Error> {
@@ -70,13 +154,89 @@ impl EnvMap {
             .cloned()
             .ok_or_else(|| Error::NotPresent(key.to_owned()))
     }
+
+    /// Inserts a variable, returning the previous value.
+    ///
+    /// A new key is added at the end. An existing key keeps its position.
+    pub fn insert(&mut self, key: String, value: String) -> Option<String> {
+        if let Some(v) = self.map.get_mut(&key) {
+            return Some(std::mem::replace(v, value));
+        }
+        self.order.push(key.clone());
+        self.map.insert(key, value)
+    }
+
+    /// Removes a variable, returning its value. The order of the other variables is kept.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let value = self.map.remove(key)?;
+        self.order.retain(|k| k != key);
+        Some(value)
+    }
+
+    /// Returns a mutable reference to the value of a variable.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut String> {
+        self.map.get_mut(key)
+    }
+
+    /// Keeps only the variables for which the predicate returns `true`.
+    pub fn retain<F: FnMut(&str, &str) -> bool>(&mut self, mut f: F) {
+        self.map.retain(|k, v| f(k, v));
+        self.order.retain(|k| self.map.contains_key(k));
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+
+    /// Returns an iterator over the variables in insertion order.
+    #[must_use]
+    pub fn iter(&self) -> EnvMapIter<'_> {
+        EnvMapIter {
+            keys: self.order.iter(),
+            map: &self.map,
+        }
+    }
+
+    /// Returns an iterator over the keys in insertion order.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.order.iter()
+    }
+
+    /// Returns an iterator over the values in insertion order.
+    pub fn values(&self) -> impl Iterator<Item = &String> {
+        self.iter().map(|(_, v)| v)
+    }
 }
 
-pub use crate::err::Error;
+pub use crate::{
+    config::EnvConfig,
+    document::{Document, EntryNode, Node},
+    entry::{Entries, Entry, Position},
+    env_file::EnvFile,
+    err::{Error, ParseErrorKind},
+    provenance::{Origin, Provenance, ProvenanceMap, Shadowed},
+    search::Search,
+    substitution::{Chain, FileOnly, ProcessEnv, SubstitutionSource},
+    validate::{Issue, IssueKind, Policy, Severity, ValidateOptions, ValidationReport},
+    write::WriteOptions,
+};
+
+#[cfg(feature = "serde")]
+pub use crate::de::Deserializer;
+
+#[cfg(feature = "schema")]
+pub use crate::schema::{KeySchema, Schema, ValueType};
 
 #[cfg(feature = "macros")]
 pub use dotenvy_macros::*;
 
+/// Used by `#[derive(EnvConfig)]`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::{config::read_var, typed::parse_bool};
+}
+
 /// Fetches the environment variable `key` from the current process.
 ///
 /// This is `std_env_var` but with an error type of `dotenvy::Error`.
@@ -124,11 +284,53 @@ pub enum EnvSequence {
     InputThenEnv,
 }
 
-#[derive(Default)]
-pub struct EnvLoader<'a> {
+/// A buffered input of an `EnvLoader`.
+type InputBuf<'a> = BufReader<Box<dyn Read + 'a>>;
+
+/// An input of an `EnvLoader`.
+struct Input<'a> {
     path: Option<PathBuf>,
     reader: Option<Box<dyn Read + 'a>>,
+    /// A search for the file, which is done when the input is opened.
+    search: Option<Search>,
+    /// Whether a file that does not exist is skipped.
+    optional: bool,
+}
+
+impl<'a> Input<'a> {
+    /// Opens the input, returning it with its path, which is the path that was found for a search.
+    fn open(self) -> Result<(Option<PathBuf>, InputBuf<'a>), crate::Error> {
+        let path = match (&self.reader, self.search) {
+            (None, Some(search)) => Some(
+                search
+                    .find()
+                    .map_err(|io_err| crate::Error::from((io_err, search.start_path())))?,
+            ),
+            _ => self.path,
+        };
+        let rdr = if let Some(rdr) = self.reader {
+            rdr
+        } else if let Some(path) = &path {
+            let file =
+                File::open(path).map_err(|io_err| crate::Error::from((io_err, path.clone())))?;
+            Box::new(file)
+        } else {
+            // an input always has a reader, a path or a search
+            return Err(Error::NoInput);
+        };
+        Ok((path, BufReader::new(rdr)))
+    }
+}
+
+#[derive(Default)]
+pub struct EnvLoader<'a> {
+    /// The inputs in the order they are loaded.
+    inputs: Vec<Input<'a>>,
     sequence: EnvSequence,
+    substitution_source: Option<Box<dyn SubstitutionSource + 'a>>,
+    substitution_disabled: bool,
+    deferred_substitution: bool,
+    includes: bool,
 }
 
 impl<'a> EnvLoader<'a> {
@@ -142,30 +344,194 @@ impl<'a> EnvLoader<'a> {
     ///
     /// This operation is infallible. IO is deferred until `load` or `load_and_modify` is called.
     pub fn with_path<P: AsRef<Path>>(path: P) -> Self {
-        Self {
-            path: Some(path.as_ref().to_owned()),
-            ..Default::default()
-        }
+        Self::default().add_path(path)
     }
 
     /// Creates a new `EnvLoader` with the reader as input.
     ///
     /// This operation is infallible. IO is deferred until `load` or `load_and_modify` is called.
     pub fn with_reader<R: Read + 'a>(rdr: R) -> Self {
+        Self::default().add_reader(rdr)
+    }
+
+    /// Creates a new `EnvLoader` with the file found by the search as input.
+    ///
+    /// This operation is infallible. The search is done when `load` or `load_and_modify` is called,
+    /// and fails with `Error::Io` if the file is not found.
+    #[must_use]
+    pub fn with_search(search: Search) -> Self {
+        Self::default().add_search(search)
+    }
+
+    /// Creates a new `EnvLoader` with the env files of the profile in the directory as input.
+    ///
+    /// The files are loaded as layers in increasing priority: `.env`, `.env.local`, `.env.{profile}` and `.env.{profile}.local`.
+    /// Without a profile, only `.env` and `.env.local` are loaded.
+    /// Files that do not exist are skipped. Use [`input_paths`](Self::input_paths) to find which files are used.
+    ///
+    /// This operation is infallible. IO is deferred until `load` or `load_and_modify` is called.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dotenvy::EnvLoader;
+    ///
+    /// let loader = EnvLoader::with_profile(".", Some("production"));
+    /// for path in loader.input_paths() {
+    ///     println!("loading {}", path.display());
+    /// }
+    /// let env_map = loader.load()?;
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn with_profile<P: AsRef<Path>>(dir: P, profile: Option<&str>) -> Self {
+        let dir = dir.as_ref();
+        let mut names = vec![".env".to_owned(), ".env.local".to_owned()];
+        if let Some(profile) = profile {
+            names.push(format!(".env.{profile}"));
+            names.push(format!(".env.{profile}.local"));
+        }
+        let inputs = names
+            .into_iter()
+            .map(|name| Input {
+                path: Some(dir.join(name)),
+                reader: None,
+                search: None,
+                optional: true,
+            })
+            .collect();
         Self {
-            reader: Some(Box::new(rdr)),
+            inputs,
             ..Default::default()
         }
     }
 
-    /// Sets the path to the specified path.
+    /// Creates a new `EnvLoader` with the env files of the profile named by the variable in the existing environment.
+    ///
+    /// For example, with `APP_ENV=production`, this is the same as `with_profile(dir, Some("production"))`.
+    /// If the variable is unset or empty, no profile is used.
+    pub fn with_profile_var<P: AsRef<Path>>(dir: P, var: &str) -> Self {
+        let profile = env::var(var).ok().filter(|profile| !profile.is_empty());
+        Self::with_profile(dir, profile.as_deref())
+    }
+
+    /// Returns the paths of the inputs that will be loaded, in order.
+    ///
+    /// Searches are done to find their paths. Optional files that do not exist are left out,
+    /// as are searches that find no file and readers without a path.
+    #[must_use]
+    pub fn input_paths(&self) -> Vec<PathBuf> {
+        self.inputs
+            .iter()
+            .filter_map(|input| {
+                if input.reader.is_some() {
+                    return input.path.clone();
+                }
+                if let Some(search) = &input.search {
+                    return search.find().ok();
+                }
+                let path = input.path.clone()?;
+                (!input.optional || path.exists()).then_some(path)
+            })
+            .collect()
+    }
+
+    /// Sets the path of the last added input to the specified path.
     ///
     /// This is useful when constructing with a reader, but still desiring a path to be used in the error message context.
     ///
     /// If a reader exists and a path is specified, loading will be done using the reader.
     #[must_use]
     pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
-        self.path = Some(path.as_ref().to_owned());
+        let path = Some(path.as_ref().to_owned());
+        match self.inputs.last_mut() {
+            Some(input) => input.path = path,
+            None => self.inputs.push(Input {
+                path,
+                reader: None,
+                search: None,
+                optional: false,
+            }),
+        }
+        self
+    }
+
+    /// Marks the last added input as optional. Inputs are required by default.
+    ///
+    /// An optional file that does not exist, or a search that finds no file, is treated as an empty input,
+    /// so the sequence is still followed. Other errors, such as a file that cannot be read, still fail with `Error::Io`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::{EnvLoader, EnvSequence};
+    ///
+    /// let env_map = EnvLoader::with_path("non-existent-env")
+    ///     .optional(true)
+    ///     .sequence(EnvSequence::InputThenEnv)
+    ///     .load()?;
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn optional(mut self, optional: bool) -> Self {
+        if let Some(input) = self.inputs.last_mut() {
+            input.optional = optional;
+        }
+        self
+    }
+
+    /// Adds the file at the path as another layer of input.
+    ///
+    /// Inputs are loaded in the order they are added, and values in later inputs override values in earlier ones.
+    /// Together, they take the place of the input in the sequence. Substitution in an input sees the values of earlier inputs,
+    /// after the substitution source, as if they were defined earlier in the same input.
+    ///
+    /// Errors carry the path of the input they occurred in.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dotenvy::EnvLoader;
+    ///
+    /// // `.env.local` overrides `.env`, and the existing environment overrides both
+    /// let env_map = EnvLoader::with_path(".env").add_path(".env.local").load()?;
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn add_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.inputs.push(Input {
+            path: Some(path.as_ref().to_owned()),
+            reader: None,
+            search: None,
+            optional: false,
+        });
+        self
+    }
+
+    /// Adds the file found by the search as another layer of input.
+    ///
+    /// See [`add_path`](Self::add_path) for how inputs are layered.
+    #[must_use]
+    pub fn add_search(mut self, search: Search) -> Self {
+        self.inputs.push(Input {
+            path: None,
+            reader: None,
+            search: Some(search),
+            optional: false,
+        });
+        self
+    }
+
+    /// Adds the reader as another layer of input.
+    ///
+    /// See [`add_path`](Self::add_path) for how inputs are layered. Use [`path`](Self::path) afterwards to name the reader in errors.
+    #[must_use]
+    pub fn add_reader<R: Read + 'a>(mut self, rdr: R) -> Self {
+        self.inputs.push(Input {
+            path: None,
+            reader: Some(Box::new(rdr)),
+            search: None,
+            optional: false,
+        });
         self
     }
 
@@ -176,35 +542,139 @@ impl<'a> EnvLoader<'a> {
         self
     }
 
-    fn buf(self) -> Result<BufReader<Box<dyn Read + 'a>>, crate::Error> {
-        let rdr = if let Some(rdr) = self.reader {
-            rdr
-        } else if let Some(path) = self.path {
-            let file = File::open(&path).map_err(|io_err| crate::Error::from((io_err, path)))?;
-            Box::new(file)
-        } else {
+    /// Sets the source of values for variable substitution.
+    ///
+    /// The default is [`ProcessEnv`], so `$NAME` expands to the value in the existing environment if it is set there, regardless of the sequence.
+    /// Use [`FileOnly`] or an [`EnvMap`] for expansion that does not depend on the existing environment.
+    #[must_use]
+    pub fn substitution_source<S: SubstitutionSource + 'a>(mut self, source: S) -> Self {
+        self.substitution_source = Some(Box::new(source));
+        self
+    }
+
+    /// Enables or disables variable substitution. It is enabled by default.
+    ///
+    /// When disabled, `$` is treated like any other character, so values such as bcrypt hashes are loaded verbatim.
+    #[must_use]
+    pub const fn substitution(mut self, enabled: bool) -> Self {
+        self.substitution_disabled = !enabled;
+        self
+    }
+
+    /// Defers variable substitution until the entire input has been read. It is disabled by default.
+    ///
+    /// When enabled, a value may reference a key defined later in the input, and references are resolved recursively in dependency order.
+    /// References that form a cycle, such as `A=${B}` and `B=${A}`, fail with `Error::SubstitutionCycle`.
+    /// A value referencing its own key, such as `PORT=${PORT:-8080}`, only sees the key in the substitution source.
+    ///
+    /// If a key is defined more than once, its last definition is used for all references.
+    /// Unlike reading in order, a definition does not see an earlier definition of its own key,
+    /// so `A=1` followed by `A=${A}2` loads `A` as `2` rather than `12`.
+    #[must_use]
+    pub const fn deferred_substitution(mut self, enabled: bool) -> Self {
+        self.deferred_substitution = enabled;
+        self
+    }
+
+    /// Enables `#include path` and `source path` directives. They are disabled by default.
+    ///
+    /// A directive reads the variables of another file in its place, as if they were defined on that line.
+    /// The path is relative to the directory of the including file, or to the current directory for a reader without a path.
+    /// Included files may include other files, up to 16 levels deep, but may not include a file that is already being included.
+    ///
+    /// Errors in an included file, including a file that cannot be opened,
+    /// are wrapped in `Error::Include` with the location of each directive through which the file was included.
+    /// When disabled, `#include` lines are comments and `source` lines fail to parse.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dotenvy::EnvLoader;
+    ///
+    /// // .env starts with `#include ../shared/.env.base`
+    /// let env_map = EnvLoader::with_path(".env").includes(true).load()?;
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    #[must_use]
+    pub const fn includes(mut self, enabled: bool) -> Self {
+        self.includes = enabled;
+        self
+    }
+
+    /// Opens each input, in the order they are loaded.
+    fn layers(mut self) -> Result<Vec<Layer<'a>>, crate::Error> {
+        if self.inputs.is_empty() {
             // only `EnvLoader::default` would have no reader or path
             return Err(Error::NoInput);
+        }
+        let source: Rc<dyn SubstitutionSource + 'a> = match self.substitution_source.take() {
+            Some(source) => Rc::from(source),
+            None => Rc::new(ProcessEnv),
         };
-        Ok(BufReader::new(rdr))
+        let substitution = !self.substitution_disabled;
+        let deferred_substitution = self.deferred_substitution;
+        let includes = self.includes;
+        let mut layers = Vec::new();
+        for input in self.inputs {
+            let optional = input.optional;
+            let (path, buf) = match input.open() {
+                Ok(opened) => opened,
+                Err(e) if optional && e.not_found() => continue,
+                Err(e) => return Err(e),
+            };
+            let iter = Iter::new(buf)
+                .substitution_source(Box::new(Rc::clone(&source)))
+                .substitution(substitution)
+                .deferred_substitution(deferred_substitution)
+                .path(path.clone())
+                .includes(includes);
+            layers.push(Layer { path, iter });
+        }
+        Ok(layers)
+    }
+
+    /// Loads each input with the function, with values in later inputs overriding values in earlier ones.
+    fn load_layers<F>(self, mut load_fn: F) -> Result<EnvMap, crate::Error>
+    where
+        F: FnMut(Iter<'a, InputBuf<'a>>) -> Result<EnvMap, ParseBufError>,
+    {
+        let mut env_map = EnvMap::new();
+        for Layer { path, iter } in self.layers()? {
+            let iter = iter.earlier_values(env_map.map.clone());
+            let input = load_fn(iter).map_err(|e| crate::Error::from((e, path)))?;
+            env_map.extend(input);
+        }
+        Ok(env_map)
     }
 
     fn load_input(self) -> Result<EnvMap, crate::Error> {
-        let path = self.path.clone();
-        let iter = Iter::new(self.buf()?);
-        iter.load().map_err(|e| ((e, path).into()))
+        self.load_layers(Iter::load)
+    }
+
+    fn load_input_lenient(self) -> Result<(EnvMap, Vec<crate::Error>), crate::Error> {
+        let mut env_map = EnvMap::new();
+        let mut errors = Vec::new();
+        for Layer { path, iter } in self.layers()? {
+            let (input, layer_errors) = iter
+                .earlier_values(env_map.map.clone())
+                .load_lenient()
+                .map_err(|e| crate::Error::from((e, path.clone())))?;
+            errors.extend(
+                layer_errors
+                    .into_iter()
+                    .map(|e| crate::Error::from((e, path.clone()))),
+            );
+            env_map.extend(input);
+        }
+        Ok((env_map, errors))
     }
 
     unsafe fn load_input_and_modify(self) -> Result<EnvMap, crate::Error> {
-        let path = self.path.clone();
-        let iter = Iter::new(self.buf()?);
-        unsafe { iter.load_and_modify() }.map_err(|e| ((e, path).into()))
+        self.load_layers(|iter| unsafe { iter.load_and_modify() })
     }
 
     unsafe fn load_input_and_modify_override(self) -> Result<EnvMap, crate::Error> {
-        let path = self.path.clone();
-        let iter = Iter::new(self.buf()?);
-        unsafe { iter.load_and_modify_override() }.map_err(|e| ((e, path).into()))
+        self.load_layers(|iter| unsafe { iter.load_and_modify_override() })
     }
 
     /// Loads environment variables into a hash map.
@@ -228,6 +698,194 @@ impl<'a> EnvLoader<'a> {
         }
     }
 
+    /// Loads environment variables into a hash map, recording where each value came from.
+    ///
+    /// The map is the same as the one returned by `load`. For each key, the provenance records the origin of its value,
+    /// i.e. the line of the input or the existing environment, and the values it replaced.
+    /// A key defined more than once in the input shadows its earlier definitions,
+    /// unless substitution is deferred, in which case only the last definition is recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::{EnvLoader, EnvSequence, Origin};
+    /// use std::io::Cursor;
+    ///
+    /// let input = "LOG_LEVEL=info\nLOG_LEVEL=debug";
+    /// let (env_map, provenance) = EnvLoader::with_reader(Cursor::new(input))
+    ///     .path(".env")
+    ///     .sequence(EnvSequence::InputOnly)
+    ///     .load_with_provenance()?;
+    ///
+    /// let log_level = &provenance["LOG_LEVEL"];
+    /// assert_eq!(log_level.origin.to_string(), ".env:2");
+    /// for shadowed in &log_level.shadowed {
+    ///     println!("LOG_LEVEL={} from {} was overridden", shadowed.value, shadowed.origin);
+    /// }
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn load_with_provenance(self) -> Result<(EnvMap, ProvenanceMap), crate::Error> {
+        let mut env_map = EnvMap::new();
+        let mut provenance = ProvenanceMap::new();
+        let load_env = |env_map: &mut EnvMap, provenance: &mut ProvenanceMap| {
+            for (k, v) in env::vars() {
+                let previous = env_map.insert(k.clone(), v);
+                provenance.record(&k, Origin::ProcessEnv, previous);
+            }
+        };
+        let sequence = self.sequence.clone();
+        if sequence == EnvSequence::EnvThenInput || sequence == EnvSequence::EnvOnly {
+            load_env(&mut env_map, &mut provenance);
+        }
+        if sequence != EnvSequence::EnvOnly {
+            for entry in self.entries()? {
+                let entry = entry?;
+                let origin = Origin::Input {
+                    path: entry.path,
+                    line: entry.position.line,
+                };
+                let previous = env_map.insert(entry.key.clone(), entry.value);
+                provenance.record(&entry.key, origin, previous);
+            }
+        }
+        if sequence == EnvSequence::InputThenEnv {
+            load_env(&mut env_map, &mut provenance);
+        }
+        Ok((env_map, provenance))
+    }
+
+    /// Loads environment variables into a hash map, and validates them against an example file such as `.env.example`.
+    ///
+    /// Every key in the example is required, and its value in the example is ignored. Keys from the input that are not
+    /// in the example are extra, but keys from the existing environment are not. Each issue is a warning or an error
+    /// as set by the options, and has the origin of the key: its line in the example if it is missing, and in the input otherwise.
+    ///
+    /// Fails with `Error::Validation` if there are any errors. Otherwise the report holds any warnings.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dotenvy::{EnvLoader, Policy, ValidateOptions};
+    ///
+    /// let options = ValidateOptions::new().extra(Policy::Error);
+    /// let (env_map, report) = EnvLoader::new().validate(".env.example", &options)?;
+    /// for warning in report.warnings() {
+    ///     eprintln!("warning: {warning}");
+    /// }
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn validate<P: AsRef<Path>>(
+        self,
+        example: P,
+        options: &ValidateOptions,
+    ) -> Result<(EnvMap, ValidationReport), crate::Error> {
+        let (example, example_provenance) = EnvLoader::with_path(example)
+            .sequence(EnvSequence::InputOnly)
+            .substitution(false)
+            .load_with_provenance()?;
+        let (env_map, provenance) = self.load_with_provenance()?;
+        let report = validate::validate(
+            &env_map,
+            Some(&provenance),
+            &example,
+            Some(&example_provenance),
+            options,
+        );
+        if report.has_errors() {
+            return Err(Error::Validation(report));
+        }
+        Ok((env_map, report))
+    }
+
+    /// Loads environment variables into a hash map, applies the defaults of the schema, and validates the variables against it.
+    ///
+    /// Fails with `Error::Validation` listing every violation, each with the origin of the value,
+    /// or of the declaration in the schema for a required variable that is not set. Requires the `schema` feature.
+    #[cfg(feature = "schema")]
+    pub fn load_with_schema(self, schema: &Schema) -> Result<EnvMap, crate::Error> {
+        let (mut env_map, mut provenance) = self.load_with_provenance()?;
+        schema.apply_defaults_with_provenance(&mut env_map, Some(&mut provenance));
+        let report = schema.validate_with_provenance(&env_map, Some(&provenance));
+        if report.has_errors() {
+            return Err(Error::Validation(report));
+        }
+        Ok(env_map)
+    }
+
+    /// Returns an iterator over the entries of the input, in the order they are defined.
+    ///
+    /// Each entry is read, unquoted and expanded as the iterator advances, and carries the position at which it is defined.
+    /// Unlike `load`, entries are not collected into a map, so a key defined more than once is yielded for each definition.
+    /// The sequence is ignored and the existing environment is not included.
+    /// With several inputs, the entries of each input are yielded in turn, with the path of their input.
+    ///
+    /// A line that cannot be parsed yields an error, and iteration continues with the next line.
+    /// Iteration stops after an IO error.
+    ///
+    /// With [`deferred_substitution`](Self::deferred_substitution), each input is read entirely before its first entry is yielded,
+    /// and a key defined more than once is yielded once, in the order keys first appear, with the value and position of its last definition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::EnvLoader;
+    /// use std::io::Cursor;
+    ///
+    /// let input = "HOST=localhost\n# comment\nexport PORT=8080";
+    /// for entry in EnvLoader::with_reader(Cursor::new(input)).entries()? {
+    ///     let entry = entry?;
+    ///     println!("{}: {}={}", entry.position, entry.key, entry.value);
+    /// }
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn entries(self) -> Result<Entries<'a>, crate::Error> {
+        Entries::new(self.layers()?)
+    }
+
+    /// Loads environment variables into a hash map, continuing after errors in the input.
+    ///
+    /// Returns the variables that could be loaded along with every error found in the input, so that all problems can be reported at once.
+    /// Lines that cannot be parsed and values that cannot be expanded are skipped.
+    /// It is up to the caller to decide whether the errors are fatal.
+    ///
+    /// Errors that prevent reading the input, such as IO errors, are still returned as `Err`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::{EnvLoader, EnvSequence};
+    /// use std::io::Cursor;
+    ///
+    /// let input = "HOST=localhost\nPORT 8080\nNAME='app";
+    /// let (env_map, errors) = EnvLoader::with_reader(Cursor::new(input))
+    ///     .sequence(EnvSequence::InputOnly)
+    ///     .load_lenient()?;
+    ///
+    /// assert_eq!(env_map.var("HOST")?, "localhost");
+    /// for e in &errors {
+    ///     eprintln!("{e}");
+    /// }
+    /// assert_eq!(errors.len(), 2);
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn load_lenient(self) -> Result<(EnvMap, Vec<crate::Error>), crate::Error> {
+        match self.sequence {
+            EnvSequence::EnvOnly => Ok((env::vars().collect(), Vec::new())),
+            EnvSequence::EnvThenInput => {
+                let mut existing: EnvMap = env::vars().collect();
+                let (input, errors) = self.load_input_lenient()?;
+                existing.extend(input);
+                Ok((existing, errors))
+            }
+            EnvSequence::InputOnly => self.load_input_lenient(),
+            EnvSequence::InputThenEnv => {
+                let (mut input, errors) = self.load_input_lenient()?;
+                input.extend(env::vars());
+                Ok((input, errors))
+            }
+        }
+    }
+
     /// Loads environment variables into a hash map, modifying the existing environment.
     ///
     /// This calls `std::env::set_var` internally and is not thread-safe.
@@ -264,7 +922,10 @@ impl<'a> EnvLoader<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{EnvLoader, EnvSequence};
+    use crate::{
+        test_util::TempDir, EnvLoader, EnvMap, EnvSequence, FileOnly, Origin, Position, ProcessEnv,
+        Shadowed, SubstitutionSource,
+    };
     use std::{env, error, io::Cursor};
 
     #[test]
@@ -408,6 +1069,588 @@ Line 6
         Ok(())
     }
 
+    #[test]
+    fn test_required() {
+        let s = "FOO=bar\n\nURL=postgres://${DB_HOST_UNSET:?DB_HOST must be set}/app";
+        let err = EnvLoader::with_reader(Cursor::new(s))
+            .path(".env")
+            .sequence(EnvSequence::InputOnly)
+            .load()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ".env:3: DB_HOST_UNSET: DB_HOST must be set"
+        );
+        assert!(matches!(
+            err,
+            crate::Error::Required { ref name, ref message, path: Some(_), line: 3 }
+                if name == "DB_HOST_UNSET" && message == "DB_HOST must be set"
+        ));
+    }
+
+    #[test]
+    fn test_order() -> Result<(), crate::Error> {
+        let s = "B=1\nA=2\nB=3\nORDER_C=4";
+        let env_map = EnvLoader::with_reader(Cursor::new(s))
+            .sequence(EnvSequence::InputOnly)
+            .load()?;
+        assert_eq!(env_map.keys().collect::<Vec<_>>(), ["B", "A", "ORDER_C"]);
+        assert_eq!(env_map.values().collect::<Vec<_>>(), ["3", "2", "4"]);
+        assert_eq!(
+            format!("{env_map:?}"),
+            r#"{"B": "3", "A": "2", "ORDER_C": "4"}"#
+        );
+
+        temp_env::with_var("ORDER_C", Some("env"), || {
+            let env_map = EnvLoader::with_reader(Cursor::new(s))
+                .sequence(EnvSequence::InputThenEnv)
+                .load()?;
+            let keys = env_map.keys().take(3).collect::<Vec<_>>();
+            assert_eq!(keys, ["B", "A", "ORDER_C"]);
+            assert_eq!(env_map.var("ORDER_C")?, "env");
+
+            let env_map = EnvLoader::with_reader(Cursor::new(s))
+                .sequence(EnvSequence::EnvThenInput)
+                .load()?;
+            let position = |key: &str| env_map.keys().position(|k| k == key);
+            assert!(position("ORDER_C") < position("B"));
+            assert_eq!(position("A"), Some(env_map.len() - 1));
+            assert_eq!(env_map.var("ORDER_C")?, "4");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_load_with_provenance() -> Result<(), crate::Error> {
+        let s = "PROV_A=1\nPROV_B=2\nPROV_A=3";
+        let input = |line| Origin::Input {
+            path: Some(".env".into()),
+            line,
+        };
+        temp_env::with_var("PROV_B", Some("env"), || {
+            let (env_map, provenance) = EnvLoader::with_reader(Cursor::new(s))
+                .path(".env")
+                .sequence(EnvSequence::InputThenEnv)
+                .load_with_provenance()?;
+            assert_eq!(env_map.var("PROV_A")?, "3");
+            assert_eq!(env_map.var("PROV_B")?, "env");
+            assert_eq!(provenance.len(), env_map.len());
+
+            let prov_a = &provenance["PROV_A"];
+            assert_eq!(prov_a.origin, input(3));
+            assert_eq!(
+                prov_a.shadowed,
+                [Shadowed {
+                    origin: input(1),
+                    value: "1".to_owned()
+                }]
+            );
+            let prov_b = &provenance["PROV_B"];
+            assert_eq!(prov_b.origin, Origin::ProcessEnv);
+            assert_eq!(prov_b.shadowed[0].origin, input(2));
+
+            let (_, provenance) = EnvLoader::with_reader(Cursor::new(s))
+                .path(".env")
+                .sequence(EnvSequence::EnvThenInput)
+                .load_with_provenance()?;
+            assert_eq!(provenance.origin("PROV_B"), Some(&input(2)));
+            assert_eq!(provenance["PROV_B"].shadowed[0].origin, Origin::ProcessEnv);
+            assert_eq!(provenance["PROV_B"].shadowed[0].value, "env");
+
+            let (_, provenance) = EnvLoader::with_reader(Cursor::new(s))
+                .sequence(EnvSequence::EnvOnly)
+                .load_with_provenance()?;
+            assert_eq!(provenance.origin("PROV_B"), Some(&Origin::ProcessEnv));
+            assert!(provenance.origin("PROV_A").is_none());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_layers() -> Result<(), crate::Error> {
+        let base = "LAYER_HOST=localhost\nLAYER_PORT=80\nLAYER_PATH=/bin";
+        let local =
+            "LAYER_PORT=8080\nLAYER_URL=${LAYER_HOST}:${LAYER_PORT}\nLAYER_PATH=${LAYER_PATH}:/opt";
+        temp_env::with_var("LAYER_HOST", Some("example.com"), || {
+            let env_map = EnvLoader::with_reader(Cursor::new(base))
+                .add_reader(Cursor::new(local))
+                .substitution_source(FileOnly)
+                .sequence(EnvSequence::InputThenEnv)
+                .load()?;
+            assert_eq!(env_map.var("LAYER_HOST")?, "example.com");
+            assert_eq!(env_map.var("LAYER_PORT")?, "8080");
+            assert_eq!(env_map.var("LAYER_URL")?, "localhost:8080");
+            assert_eq!(env_map.var("LAYER_PATH")?, "/bin:/opt");
+
+            let deferred = EnvLoader::with_reader(Cursor::new(base))
+                .add_reader(Cursor::new(local))
+                .substitution_source(FileOnly)
+                .deferred_substitution(true)
+                .sequence(EnvSequence::InputOnly)
+                .load()?;
+            assert_eq!(deferred.len(), 4);
+            assert_eq!(deferred.var("LAYER_URL")?, "localhost:8080");
+            assert_eq!(deferred.var("LAYER_PATH")?, "/bin:/opt");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_layers_error_path() {
+        let err = EnvLoader::with_reader(Cursor::new("A=1"))
+            .path(".env")
+            .add_reader(Cursor::new("B=1\nC 2"))
+            .path(".env.local")
+            .sequence(EnvSequence::InputOnly)
+            .load()
+            .unwrap_err();
+        assert_eq!(err.to_string(), ".env.local:2:3: expected '='");
+
+        let err = EnvLoader::with_reader(Cursor::new("A=1"))
+            .add_path("does-not-exist.env")
+            .load()
+            .unwrap_err();
+        assert!(err.not_found());
+    }
+
+    #[test]
+    fn test_layers_entries() -> Result<(), crate::Error> {
+        let entries = EnvLoader::with_reader(Cursor::new("A=1\nB=2"))
+            .path(".env")
+            .add_reader(Cursor::new("A=$B$A"))
+            .path(".env.local")
+            .substitution_source(FileOnly)
+            .entries()?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let entries = entries
+            .iter()
+            .map(|entry| {
+                let path = entry.path.as_deref().and_then(|path| path.to_str());
+                (entry.key.as_str(), entry.value.as_str(), path)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                ("A", "1", Some(".env")),
+                ("B", "2", Some(".env")),
+                ("A", "21", Some(".env.local"))
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile() -> Result<(), crate::Error> {
+        let dir = TempDir::new("profile");
+        dir.write(".env", "PROFILE_A=env\nPROFILE_B=env\nPROFILE_C=env");
+        dir.write(
+            ".env.production",
+            "PROFILE_B=production\nPROFILE_C=production",
+        );
+        dir.write(".env.production.local", "PROFILE_C=production.local");
+
+        let loader =
+            EnvLoader::with_profile(&dir, Some("production")).sequence(EnvSequence::InputOnly);
+        assert_eq!(
+            loader.input_paths(),
+            [
+                dir.join(".env"),
+                dir.join(".env.production"),
+                dir.join(".env.production.local")
+            ]
+        );
+        let env_map = loader.load()?;
+        assert_eq!(env_map.var("PROFILE_A")?, "env");
+        assert_eq!(env_map.var("PROFILE_B")?, "production");
+        assert_eq!(env_map.var("PROFILE_C")?, "production.local");
+
+        temp_env::with_var_unset("PROFILE_SELECTOR", || {
+            let loader = EnvLoader::with_profile_var(&dir, "PROFILE_SELECTOR");
+            assert_eq!(loader.input_paths(), [dir.join(".env")]);
+        });
+        temp_env::with_var("PROFILE_SELECTOR", Some("staging"), || {
+            let env_map = EnvLoader::with_profile_var(&dir, "PROFILE_SELECTOR")
+                .sequence(EnvSequence::InputOnly)
+                .load()?;
+            assert_eq!(env_map.var("PROFILE_C")?, "env");
+            Ok::<_, crate::Error>(())
+        })?;
+
+        let missing = dir.to_path_buf();
+        drop(dir);
+        let env_map = EnvLoader::with_profile(&missing, None)
+            .sequence(EnvSequence::InputOnly)
+            .load()?;
+        assert!(env_map.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_optional() -> Result<(), crate::Error> {
+        temp_env::with_var("OPTIONAL_HOST", Some("abc"), || {
+            let env_map = EnvLoader::with_path("non-existent-env")
+                .optional(true)
+                .load()?;
+            assert_eq!(env_map.var("OPTIONAL_HOST")?, "abc");
+
+            let env_map = EnvLoader::with_path("non-existent-env")
+                .optional(true)
+                .add_reader(Cursor::new("OPTIONAL_PORT=80"))
+                .sequence(EnvSequence::InputOnly)
+                .load()?;
+            assert_eq!(env_map.len(), 1);
+            Ok::<_, crate::Error>(())
+        })?;
+
+        let err = EnvLoader::with_path("non-existent-env").load().unwrap_err();
+        assert!(err.not_found());
+
+        // a directory can be opened, but not read
+        let err = EnvLoader::with_path(env::temp_dir())
+            .optional(true)
+            .load()
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Io(_, Some(_))));
+        assert!(!err.not_found());
+        Ok(())
+    }
+
+    #[test]
+    fn test_includes() -> Result<(), crate::Error> {
+        let dir = TempDir::new("includes");
+        let service = dir.join("service");
+        dir.write(
+            "shared/.env.base",
+            "INC_BASE=1\nINC_HOST=base\nINC_LATER=later",
+        );
+        dir.write(
+            "service/.env",
+            "INC_URL=${INC_LATER}\n#include ../shared/.env.base\nINC_HOST=service\nINC_PORT=${INC_HOST}:${INC_BASE}",
+        );
+        let loader = || {
+            EnvLoader::with_path(service.join(".env"))
+                .sequence(EnvSequence::InputOnly)
+                .substitution_source(FileOnly)
+        };
+
+        let env_map = loader().includes(true).load()?;
+        assert_eq!(env_map.var("INC_HOST")?, "service");
+        assert_eq!(env_map.var("INC_PORT")?, "service:1");
+        assert_eq!(env_map.var("INC_URL")?, "");
+        let env_map = loader().includes(true).deferred_substitution(true).load()?;
+        assert_eq!(env_map.var("INC_URL")?, "later");
+        let env_map = loader().load()?;
+        assert!(!env_map.contains_key("INC_BASE"));
+
+        let entries = loader()
+            .includes(true)
+            .entries()?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(entries[1].key, "INC_BASE");
+        assert_eq!(entries[1].path, Some(service.join("../shared/.env.base")));
+        assert_eq!(entries[4].path, Some(service.join(".env")));
+
+        // errors show where the file was included from
+        dir.write("shared/bad.env", "INC_A=1\nINC_B 2");
+        dir.write("service/bad.env", "INC_C=1\nsource '../shared/bad.env'");
+        let err = EnvLoader::with_path(service.join("bad.env"))
+            .includes(true)
+            .load()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}:2:7: expected '=' (included from {}:2)",
+                service.join("../shared/bad.env").display(),
+                service.join("bad.env").display()
+            )
+        );
+
+        dir.write("shared/req.env", "INC_R=${INC_MISSING:?required}");
+        dir.write(
+            "service/req.env",
+            "INC_S=$INC_R\n#include ../shared/req.env",
+        );
+        let err = EnvLoader::with_path(service.join("req.env"))
+            .includes(true)
+            .deferred_substitution(true)
+            .substitution_source(FileOnly)
+            .load()
+            .unwrap_err();
+        let crate::Error::Include { error, chain } = err else {
+            panic!("expected an include error, found {err:?}");
+        };
+        assert!(matches!(
+            *error,
+            crate::Error::Required { path: Some(path), line: 1, .. } if path.ends_with("shared/req.env")
+        ));
+        assert_eq!(chain.len(), 1);
+
+        dir.write("a.env", "#include b.env");
+        dir.write("b.env", "INC_D=1\n#include a.env");
+        let err = EnvLoader::with_path(dir.join("a.env"))
+            .includes(true)
+            .load()
+            .unwrap_err();
+        let crate::Error::Include { error, chain } = err else {
+            panic!("expected an include error, found {err:?}");
+        };
+        assert!(matches!(
+            *error,
+            crate::Error::LineParse {
+                kind: crate::ParseErrorKind::IncludeCycle,
+                line: 2,
+                ..
+            }
+        ));
+        assert_eq!(
+            chain,
+            [Origin::Input {
+                path: Some(dir.join("a.env")),
+                line: 1
+            }]
+        );
+
+        dir.write("missing.env", "#include does-not-exist.env");
+        let err = EnvLoader::with_path(dir.join("missing.env"))
+            .includes(true)
+            .load()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Include { error, .. } if error.not_found()
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_map() {
+        let mut env_map = [("A", "1"), ("B", "2"), ("C", "3")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect::<EnvMap>();
+        assert_eq!(
+            env_map.insert("A".to_owned(), "4".to_owned()),
+            Some("1".to_owned())
+        );
+        assert_eq!(env_map.remove("B"), Some("2".to_owned()));
+        env_map.insert("B".to_owned(), "5".to_owned());
+        env_map.retain(|k, _| k != "C");
+
+        let expected = [("A", "4"), ("B", "5")].map(|(k, v)| (k.to_owned(), v.to_owned()));
+        assert_eq!(env_map.clone().into_iter().collect::<Vec<_>>(), expected);
+        assert!((&env_map)
+            .into_iter()
+            .eq(expected.iter().map(|(k, v)| (k, v))));
+
+        let reversed = expected.into_iter().rev().collect::<EnvMap>();
+        assert_eq!(env_map, reversed);
+        assert!(env_map.contains_key("A"));
+    }
+
+    #[test]
+    fn test_line_parse_position() {
+        let s = "FOO=bar\nBAR=\"baz\nqux\nBAZ=1";
+        let err = EnvLoader::with_reader(Cursor::new(s))
+            .path(".env")
+            .sequence(EnvSequence::InputOnly)
+            .load()
+            .unwrap_err();
+        assert_eq!(err.to_string(), ".env:2:5: unterminated double quote");
+        assert!(matches!(
+            err,
+            crate::Error::LineParse {
+                kind: crate::ParseErrorKind::UnterminatedDoubleQuote,
+                path: Some(_),
+                line: 2,
+                column: 5,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_entries() -> Result<(), crate::Error> {
+        let s = "A=1\n\n# comment\n  export B = 'multi\nline'\nC=$A$D\nD 4\nA=5";
+        let entries = EnvLoader::with_reader(Cursor::new(s))
+            .path(".env")
+            .substitution_source(FileOnly)
+            .entries()?
+            .map(|entry| {
+                entry.map(|entry| {
+                    (
+                        entry.key,
+                        entry.value,
+                        entry.position.to_string(),
+                        entry.value_position.to_string(),
+                    )
+                })
+            })
+            .map(|entry| entry.map_err(|e| e.to_string()))
+            .collect::<Vec<_>>();
+
+        let expected = [
+            Ok(("A", "1", "1:1", "1:3")),
+            Ok(("B", "multi\nline", "4:10", "4:14")),
+            Ok(("C", "1", "6:1", "6:3")),
+            Err(".env:7:3: expected '='"),
+            Ok(("A", "5", "8:1", "8:3")),
+        ]
+        .map(|entry| {
+            entry
+                .map(|(k, v, p, vp)| (k.to_owned(), v.to_owned(), p.to_owned(), vp.to_owned()))
+                .map_err(str::to_owned)
+        });
+        assert_eq!(entries, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_deferred() -> Result<(), crate::Error> {
+        let s = "A=$B\nB=1\nA=$B$B";
+        let entries = EnvLoader::with_reader(Cursor::new(s))
+            .substitution_source(FileOnly)
+            .deferred_substitution(true)
+            .entries()?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            (entries[0].key.as_str(), entries[0].value.as_str()),
+            ("A", "11")
+        );
+        assert_eq!(entries[0].position, Position { line: 3, column: 1 });
+        assert_eq!(
+            (entries[1].key.as_str(), entries[1].value.as_str()),
+            ("B", "1")
+        );
+        assert_eq!(entries[1].value_position, Position { line: 2, column: 3 });
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_lenient() -> Result<(), crate::Error> {
+        let s = "A=1\nB 2\nD=${E:?E must be set}\nF=$A\nC='3";
+        for deferred in [false, true] {
+            let (env_map, errors) = EnvLoader::with_reader(Cursor::new(s))
+                .path(".env")
+                .sequence(EnvSequence::InputOnly)
+                .substitution_source(FileOnly)
+                .deferred_substitution(deferred)
+                .load_lenient()?;
+
+            assert_eq!(env_map.len(), 2);
+            assert_eq!(env_map.var("A")?, "1");
+            assert_eq!(env_map.var("F")?, "1");
+            let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+            assert_eq!(
+                errors,
+                [
+                    ".env:2:3: expected '='",
+                    ".env:3: E: E must be set",
+                    ".env:5:3: unterminated single quote",
+                ]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_lenient_no_input() {
+        assert!(matches!(
+            EnvLoader::default().load_lenient(),
+            Err(crate::Error::NoInput)
+        ));
+    }
+
+    #[test]
+    fn test_substitution_source() -> Result<(), crate::Error> {
+        temp_env::with_var("SOURCE_HOST", Some("env"), || {
+            let s = "SOURCE_PORT=8080\nURL=${SOURCE_HOST}:${SOURCE_PORT}";
+
+            let env_map = EnvLoader::with_reader(Cursor::new(s))
+                .sequence(EnvSequence::InputOnly)
+                .load()?;
+            assert_eq!(env_map.var("URL")?, "env:8080");
+
+            let env_map = EnvLoader::with_reader(Cursor::new(s))
+                .sequence(EnvSequence::InputOnly)
+                .substitution_source(FileOnly)
+                .load()?;
+            assert_eq!(env_map.var("URL")?, ":8080");
+
+            let mut source = EnvMap::new();
+            source.insert("SOURCE_HOST".to_owned(), "map".to_owned());
+            let env_map = EnvLoader::with_reader(Cursor::new(s))
+                .sequence(EnvSequence::InputOnly)
+                .substitution_source(&source)
+                .load()?;
+            assert_eq!(env_map.var("URL")?, "map:8080");
+
+            let empty = EnvMap::new();
+            let env_map = EnvLoader::with_reader(Cursor::new(s))
+                .sequence(EnvSequence::InputOnly)
+                .substitution_source(empty.chain(source).chain(ProcessEnv))
+                .load()?;
+            assert_eq!(env_map.var("URL")?, "map:8080");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_substitution_disabled() -> Result<(), crate::Error> {
+        let s = "HASH='$2b$12$abc'\nCOPY=$HASH\nPASSWORD=\"p$ss${word}\"";
+        let env_map = EnvLoader::with_reader(Cursor::new(s))
+            .sequence(EnvSequence::InputOnly)
+            .substitution(false)
+            .load()?;
+        assert_eq!(env_map.var("HASH")?, "$2b$12$abc");
+        assert_eq!(env_map.var("COPY")?, "$HASH");
+        assert_eq!(env_map.var("PASSWORD")?, "p$ss${word}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_deferred_substitution() -> Result<(), crate::Error> {
+        let s = r"
+URL=${SCHEME}://${HOST}:${DEFERRED_PORT}
+SCHEME=https
+HOST=${SCHEME}.example.com
+DEFERRED_PORT=${DEFERRED_PORT:-443}
+";
+        let env_map = EnvLoader::with_reader(Cursor::new(s))
+            .sequence(EnvSequence::InputOnly)
+            .substitution_source(FileOnly)
+            .deferred_substitution(true)
+            .load()?;
+        assert_eq!(env_map.var("URL")?, "https://https.example.com:443");
+        assert_eq!(env_map.var("HOST")?, "https.example.com");
+
+        let s = "A=${B}\nB=x${C}\nC=${A}";
+        let err = EnvLoader::with_reader(Cursor::new(s))
+            .sequence(EnvSequence::InputOnly)
+            .deferred_substitution(true)
+            .load()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::SubstitutionCycle(ref cycle, None) if cycle == &["A", "B", "C", "A"]
+        ));
+        assert_eq!(err.to_string(), "substitution cycle: A -> B -> C -> A");
+
+        // a redefinition does not see the earlier definition of its key
+        let s = "A=1\nA=${A}2";
+        let loader = || {
+            EnvLoader::with_reader(Cursor::new(s))
+                .sequence(EnvSequence::InputOnly)
+                .substitution_source(FileOnly)
+        };
+        assert_eq!(loader().load()?.var("A")?, "12");
+        assert_eq!(loader().deferred_substitution(true).load()?.var("A")?, "2");
+        Ok(())
+    }
+
     #[test]
     fn test_non_modify() -> Result<(), crate::Error> {
         temp_env::with_var("SRC", Some("env"), || {
@@ -1,45 +1,321 @@
 #![allow(clippy::module_name_repetitions)]
 
-use std::{collections::HashMap, env};
+use std::collections::{HashMap, HashSet};
 
-use crate::iter::ParseBufError;
+use crate::{
+    iter::{self, Includes, ParseBufError},
+    Entry, ParseErrorKind, Position, SubstitutionSource,
+};
 
 pub fn parse_line(
     line: &str,
-    substitution_data: &mut HashMap<String, Option<String>>,
-) -> Result<Option<(String, String)>, ParseBufError> {
-    let mut parser = LineParser::new(line, substitution_data);
+    line_number: usize,
+    substitution_data: &mut HashMap<String, String>,
+    substitution_source: &dyn SubstitutionSource,
+    substitution: bool,
+) -> Result<Option<Entry>, ParseBufError> {
+    let Some(raw) = parse_entry(line, line_number)? else {
+        return Ok(None);
+    };
+    let parsed_value = parse_value(
+        raw.value,
+        raw.value_position,
+        &mut |name| Ok(lookup(substitution_data, substitution_source, name)),
+        substitution,
+    )?;
+    substitution_data.insert(raw.key.clone(), parsed_value.clone());
+
+    Ok(Some(Entry {
+        key: raw.key,
+        value: parsed_value,
+        position: raw.position,
+        value_position: raw.value_position,
+        path: None,
+    }))
+}
+
+/// Parses the key of a line, returning it with the unexpanded value and their positions.
+pub fn parse_entry(line: &str, line_number: usize) -> Result<Option<RawEntry<'_>>, ParseBufError> {
+    let mut parser = LineParser::new(line, line_number);
     parser.parse_line()
 }
 
+/// Returns the path of an `#include path` or `source path` directive, or `None` if the line is not one.
+///
+/// `text` is the entire line and `content` is the line without its comment.
+/// The path may be quoted, but is not unescaped, and may be followed by a comment.
+pub fn parse_include(text: &str, content: &str) -> Option<String> {
+    let target = if let Some(rest) = text.trim_start().strip_prefix("#include") {
+        rest
+    } else {
+        let rest = content.trim_start().strip_prefix("source")?;
+        // `source=value` and `source = value` define a variable
+        if rest.trim_start().starts_with('=') {
+            return None;
+        }
+        rest
+    };
+    if !target.starts_with(char::is_whitespace) {
+        return None;
+    }
+    // the `#include` form is itself a comment, so a trailing comment is still part of it
+    let target = strip_comment(target.trim());
+    let unquoted = ['"', '\''].iter().find_map(|&quote| {
+        target
+            .strip_prefix(quote)
+            .and_then(|target| target.strip_suffix(quote))
+    });
+    let target = unquoted.unwrap_or(target);
+    (!target.is_empty()).then(|| target.to_owned())
+}
+
+/// Removes a comment starting with whitespace and `#` after an optionally quoted word.
+fn strip_comment(text: &str) -> &str {
+    // a quoted word ends at its closing quote, so a `#` in it does not start a comment
+    let word_end = ['"', '\''].iter().find_map(|&quote| {
+        let rest = text.strip_prefix(quote)?;
+        rest.find(quote).map(|end| end + 2)
+    });
+    let after_word = word_end.unwrap_or(0);
+    text[after_word..]
+        .match_indices('#')
+        .find(|&(index, _)| text[..after_word + index].ends_with(char::is_whitespace))
+        .map_or(text, |(index, _)| text[..after_word + index].trim_end())
+}
+
+/// Unquotes and unescapes a value, leaving variable references as they are.
+pub fn unquote_value(value: &str, start: Position) -> Result<String, ParseBufError> {
+    parse_value(value, start, &mut |_| Ok(None), false)
+}
+
+/// Checks that a value can be parsed with substitution enabled, regardless of which variables are set.
+pub fn check_value(value: &str, start: Position) -> Result<(), ParseBufError> {
+    // every variable is set, so that `${NAME:?message}` does not fail
+    parse_value(value, start, &mut |_| Ok(Some("_".to_owned())), true).map(drop)
+}
+
+/// Returns whether the key can be parsed.
+pub fn is_valid_key(key: &str) -> bool {
+    key.starts_with(is_key_start) && key.chars().all(is_key_char)
+}
+
+const fn is_key_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+const fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+/// A key with its unexpanded value.
+pub struct RawEntry<'a> {
+    pub key: String,
+    pub value: &'a str,
+    pub position: Position,
+    pub value_position: Position,
+    /// The byte offset of the key in the line.
+    pub key_offset: usize,
+    /// The byte offset of the value in the line.
+    pub value_offset: usize,
+}
+
+/// An unexpanded value, with the positions of its key and value and the includes through which it was read.
+#[derive(Clone)]
+struct RawValue {
+    value: String,
+    position: Position,
+    value_position: Position,
+    includes: Includes,
+}
+
+/// Resolves references between values regardless of the order in which they are defined.
+pub struct Resolver<'a> {
+    raw: HashMap<String, RawValue>,
+    resolved: HashMap<String, String>,
+    /// The keys being resolved, outermost first.
+    stack: Vec<String>,
+    substitution_source: &'a dyn SubstitutionSource,
+    substitution: bool,
+    /// The values of earlier inputs, used for keys that are not defined in this input.
+    earlier: Option<&'a HashMap<String, String>>,
+    /// When resolving leniently, the errors of referenced keys, which are then treated as unset.
+    errors: Option<Vec<ParseBufError>>,
+    /// The keys whose errors have been recorded.
+    failed: HashSet<String>,
+    /// Whether the error being returned is from a referenced key, and so has already been wrapped in its includes.
+    propagating: bool,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(substitution_source: &'a dyn SubstitutionSource, substitution: bool) -> Self {
+        Self {
+            raw: HashMap::new(),
+            resolved: HashMap::new(),
+            stack: Vec::new(),
+            substitution_source,
+            substitution,
+            earlier: None,
+            errors: None,
+            failed: HashSet::new(),
+            propagating: false,
+        }
+    }
+
+    /// Sets the values of earlier inputs, which are seen by references to keys that are not defined in this input.
+    pub const fn earlier(mut self, earlier: &'a HashMap<String, String>) -> Self {
+        self.earlier = Some(earlier);
+        self
+    }
+
+    /// Adds an unexpanded value, replacing an earlier definition of the key.
+    ///
+    /// `includes` are the directives through which the value was included, if any.
+    /// Returns whether the key was not defined before.
+    pub fn insert(&mut self, raw: RawEntry<'_>, includes: Includes) -> bool {
+        let value = RawValue {
+            value: raw.value.to_owned(),
+            position: raw.position,
+            value_position: raw.value_position,
+            includes,
+        };
+        self.raw.insert(raw.key, value).is_none()
+    }
+
+    /// Resolves the value of a key that has been inserted, returning it with its positions.
+    pub fn resolve_entry(&mut self, key: String) -> Result<Entry, ParseBufError> {
+        let value = self.resolve(&key)?;
+        let raw = &self.raw[&key];
+        Ok(Entry {
+            position: raw.position,
+            value_position: raw.value_position,
+            path: raw.includes.last().map(|(_, path)| path.clone()),
+            key,
+            value,
+        })
+    }
+
+    pub fn resolve(&mut self, key: &str) -> Result<String, ParseBufError> {
+        if let Some(value) = self.resolved.get(key) {
+            return Ok(value.clone());
+        }
+        if let Some(index) = self.stack.iter().position(|k| k == key) {
+            let mut cycle = self.stack[index..].to_vec();
+            cycle.push(key.to_owned());
+            return Err(ParseBufError::SubstitutionCycle(cycle));
+        }
+        let Some(raw) = self.raw.get(key).cloned() else {
+            return Ok(String::new());
+        };
+
+        let substitution = self.substitution;
+        self.stack.push(key.to_owned());
+        let parsed_value = parse_value(
+            &raw.value,
+            raw.value_position,
+            &mut |name| self.lookup(name),
+            substitution,
+        );
+        self.stack.pop();
+
+        let parsed_value = parsed_value.map_err(|e| {
+            if std::mem::take(&mut self.propagating) {
+                e
+            } else {
+                iter::include_error(&raw.includes, e)
+            }
+        })?;
+        self.resolved.insert(key.to_owned(), parsed_value.clone());
+        Ok(parsed_value)
+    }
+
+    fn lookup(&mut self, name: &str) -> Result<Option<String>, ParseBufError> {
+        if let Some(value) = self.substitution_source.lookup(name) {
+            return Ok(Some(value));
+        }
+        // a value referencing its own key only sees it in the substitution source or earlier inputs,
+        // not in an earlier definition in this input, which its last definition replaces
+        if !self.raw.contains_key(name)
+            || self.stack.last().is_some_and(|k| k == name)
+            || self.failed.contains(name)
+        {
+            return Ok(self.earlier.and_then(|earlier| earlier.get(name).cloned()));
+        }
+        match self.resolve(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => {
+                let result = self.record(name, e).map(|()| None);
+                self.propagating = result.is_err();
+                result
+            }
+        }
+    }
+
+    /// Records the error of the key if lenient, or returns it otherwise.
+    fn record(&mut self, key: &str, e: ParseBufError) -> Result<(), ParseBufError> {
+        let Some(errors) = &mut self.errors else {
+            return Err(e);
+        };
+        self.failed.insert(key.to_owned());
+        errors.push(e);
+        Ok(())
+    }
+
+    /// Resolves the keys in order, returning the values of the keys that could be resolved and the errors of the others.
+    ///
+    /// Each error is reported once. Keys that could not be resolved are omitted, and are treated as unset where referenced.
+    pub fn resolve_lenient(mut self, keys: Vec<String>) -> (Vec<Entry>, Vec<ParseBufError>) {
+        self.errors = Some(Vec::new());
+        let mut entries = Vec::new();
+        for key in keys {
+            if self.failed.contains(&key) {
+                continue;
+            }
+            match self.resolve_entry(key.clone()) {
+                Ok(entry) if !self.failed.contains(&key) => entries.push(entry),
+                Ok(_) => {}
+                Err(e) => {
+                    // always `Ok`, as the resolver is lenient
+                    let _ = self.record(&key, e);
+                }
+            }
+        }
+        (entries, self.errors.unwrap_or_default())
+    }
+}
+
 struct LineParser<'a> {
     original_line: &'a str,
-    substitution_data: &'a mut HashMap<String, Option<String>>,
+    line_number: usize,
     line: &'a str,
     pos: usize,
 }
 
 impl<'a> LineParser<'a> {
-    fn new(line: &'a str, substitution_data: &'a mut HashMap<String, Option<String>>) -> Self {
+    fn new(line: &'a str, line_number: usize) -> Self {
         LineParser {
             original_line: line,
-            substitution_data,
+            line_number,
             line: line.trim_end(), // we don’t want trailing whitespace
             pos: 0,
         }
     }
 
-    fn err(&self) -> ParseBufError {
-        ParseBufError::LineParse(self.original_line.into(), self.pos)
+    fn position(&self) -> Position {
+        Position::start_of_line(self.line_number).advance(&self.original_line[..self.pos])
+    }
+
+    fn err(&self, kind: ParseErrorKind) -> ParseBufError {
+        self.position().err(kind)
     }
 
-    fn parse_line(&mut self) -> Result<Option<(String, String)>, ParseBufError> {
+    fn parse_line(&mut self) -> Result<Option<RawEntry<'a>>, ParseBufError> {
         self.skip_whitespace();
         // if its an empty line or a comment, skip it
         if self.line.is_empty() || self.line.starts_with('#') {
             return Ok(None);
         }
 
+        let mut key_offset = self.pos;
         let mut key = self.parse_key()?;
         self.skip_whitespace();
 
@@ -47,6 +323,7 @@ impl<'a> LineParser<'a> {
         if key == "export" {
             // here we check for an optional `=`, below we throw directly when it’s not found.
             if self.expect_equal().is_err() {
+                key_offset = self.pos;
                 key = self.parse_key()?;
                 self.skip_whitespace();
                 self.expect_equal()?;
@@ -56,29 +333,27 @@ impl<'a> LineParser<'a> {
         }
         self.skip_whitespace();
 
-        if self.line.is_empty() || self.line.starts_with('#') {
-            self.substitution_data.insert(key.clone(), None);
-            return Ok(Some((key, String::new())));
-        }
-
-        let parsed_value = parse_value(self.line, self.substitution_data)?;
-        self.substitution_data
-            .insert(key.clone(), Some(parsed_value.clone()));
-
-        Ok(Some((key, parsed_value)))
+        let value = if self.line.starts_with('#') {
+            ""
+        } else {
+            self.line
+        };
+        Ok(Some(RawEntry {
+            key,
+            value,
+            position: Position::start_of_line(self.line_number)
+                .advance(&self.original_line[..key_offset]),
+            value_position: self.position(),
+            key_offset,
+            value_offset: self.pos,
+        }))
     }
 
     fn parse_key(&mut self) -> Result<String, ParseBufError> {
-        if !self
-            .line
-            .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
-        {
-            return Err(self.err());
+        if !self.line.starts_with(is_key_start) {
+            return Err(self.err(ParseErrorKind::InvalidKey));
         }
-        let index = match self
-            .line
-            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
-        {
+        let index = match self.line.find(|c: char| !is_key_char(c)) {
             Some(index) => index,
             None => self.line.len(),
         };
@@ -90,7 +365,7 @@ impl<'a> LineParser<'a> {
 
     fn expect_equal(&mut self) -> Result<(), ParseBufError> {
         if !self.line.starts_with('=') {
-            return Err(self.err());
+            return Err(self.err(ParseErrorKind::MissingEquals));
         }
         self.line = &self.line[1..];
         self.pos += 1;
@@ -113,15 +388,105 @@ enum SubstitutionMode {
     None,
     Block,
     EscapedBlock,
+    /// `${NAME:` has been read and an operator must follow.
+    Colon,
+}
+
+#[derive(Clone, Copy)]
+enum Operator {
+    /// `-`: use the word if the variable is unset.
+    Default,
+    /// `?`: fail with the word as message if the variable is unset.
+    Required,
+    /// `+`: use the word if the variable is set.
+    Alternate,
+}
+
+/// A `${NAME<op>word}` expansion whose word is still being parsed.
+struct Expansion {
+    name: String,
+    operator: Operator,
+    /// Whether the operator was prefixed with `:`, which treats an empty value like an unset one.
+    colon: bool,
+    /// The position of the `$` in the input.
+    dollar: usize,
+    /// The position in the output where the expanded word starts.
+    start: usize,
+    /// Whether the expansion started in double quotes, so that only a `}` in the same quoting ends it.
+    quoted: bool,
+    /// The value of the variable, looked up when the operator is read.
+    value: Option<String>,
+    /// Whether the expansion is inside a word that is not used, so it is parsed without being expanded.
+    skipped: bool,
+}
+
+impl Expansion {
+    /// Whether the word is expanded into the output, or into the message of a `?` error.
+    const fn uses_word(&self) -> bool {
+        !self.skipped
+            && match self.operator {
+                Operator::Alternate => self.value.is_some(),
+                Operator::Default | Operator::Required => self.value.is_none(),
+            }
+    }
+}
+
+/// A `\xHH`, `\uXXXX` or `\u{X...}` escape in double quotes whose digits are still being read.
+struct HexEscape {
+    /// The position of the backslash.
+    start: usize,
+    /// Whether the escape started with `\u` rather than `\x`.
+    unicode: bool,
+    /// Whether the digits are enclosed in braces, as in `\u{1F600}`.
+    braced: bool,
+    digits: String,
+}
+
+impl HexEscape {
+    const fn new(start: usize, unicode: bool) -> Self {
+        Self {
+            start,
+            unicode,
+            braced: false,
+            digits: String::new(),
+        }
+    }
+
+    /// Returns whether all digits have been read, if the number of digits is fixed.
+    fn is_complete(&self) -> bool {
+        match (self.unicode, self.braced) {
+            (false, _) => self.digits.len() == 2,
+            (true, false) => self.digits.len() == 4,
+            (true, true) => false,
+        }
+    }
+
+    /// Returns the character, or `None` if the digits are not a Unicode scalar value or are a NUL,
+    /// which cannot be in an environment variable.
+    fn decode(&self) -> Option<char> {
+        u32::from_str_radix(&self.digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .filter(|&c| c != '\0')
+    }
 }
 
+/// Looks up a variable by name, returning `None` if it is unset.
+type Lookup<'a> = dyn FnMut(&str) -> Result<Option<String>, ParseBufError> + 'a;
+
 fn parse_value(
     input: &str,
-    substitution_data: &HashMap<String, Option<String>>,
+    start: Position,
+    lookup: &mut Lookup,
+    substitution: bool,
 ) -> Result<String, ParseBufError> {
+    let err = |kind: ParseErrorKind, index: usize| start.advance(&input[..index]).err(kind);
+
     let mut strong_quote = false; // '
     let mut weak_quote = false; // "
+    let mut quote_start = 0;
     let mut escaped = false;
+    let mut hex_escape: Option<HexEscape> = None;
     let mut expecting_end = false;
 
     //FIXME can this be done without yet another allocation per line?
@@ -129,8 +494,46 @@ fn parse_value(
 
     let mut substitution_mode = SubstitutionMode::None;
     let mut substitution_name = String::new();
+    let mut substitution_start = 0;
+    let mut expansions: Vec<Expansion> = Vec::new();
+
+    for (index, c) in input.char_indices() {
+        if let Some(escape) = hex_escape.as_mut() {
+            if c == '{' && escape.unicode && !escape.braced && escape.digits.is_empty() {
+                escape.braced = true;
+            } else if c == '}' && escape.braced && !escape.digits.is_empty() {
+                let decoded = escape.decode();
+                output
+                    .push(decoded.ok_or_else(|| err(ParseErrorKind::InvalidEscape, escape.start))?);
+                hex_escape = None;
+            } else if c.is_ascii_hexdigit() && escape.digits.len() < 6 {
+                escape.digits.push(c);
+                if escape.is_complete() {
+                    let decoded = escape.decode();
+                    output.push(
+                        decoded.ok_or_else(|| err(ParseErrorKind::InvalidEscape, escape.start))?,
+                    );
+                    hex_escape = None;
+                }
+            } else {
+                return Err(err(ParseErrorKind::InvalidEscape, index));
+            }
+            continue;
+        }
+
+        // a `$NAME` substitution ends at the first character that cannot be part of the name,
+        // which is then handled like any other character
+        if substitution_mode == SubstitutionMode::Block
+            && !c.is_alphanumeric()
+            && !(c == '{' && substitution_name.is_empty())
+        {
+            substitution_mode = SubstitutionMode::None;
+            let name = std::mem::take(&mut substitution_name);
+            if !in_unused_word(&expansions) {
+                apply_substitution(lookup, &name, &mut output)?;
+            }
+        }
 
-    for (index, c) in input.chars().enumerate() {
         //the regex _should_ already trim whitespace off the end
         //expecting_end is meant to permit: k=v #comment
         //without affecting: k=v#comment
@@ -141,17 +544,23 @@ fn parse_value(
             } else if c == '#' {
                 break;
             }
-            return Err(ParseBufError::LineParse(input.to_owned(), index));
+            return Err(err(ParseErrorKind::UnexpectedCharacter, index));
         } else if escaped {
-            //TODO I tried handling literal \r but various issues
-            //imo not worth worrying about until there's a use case
-            //(actually handling backslash 0x10 would be a whole other matter)
-            //then there's \v \f bell hex... etc
-            match c {
-                '\\' | '\'' | '"' | '$' | ' ' => output.push(c),
-                'n' => output.push('\n'), // handle \n case
+            // C-style escapes other than \n are only decoded in double quotes
+            match (c, weak_quote) {
+                ('\\' | '\'' | '"' | '$' | ' ' | '}', _) => output.push(c),
+                ('n', _) => output.push('\n'),
+                ('t', true) => output.push('\t'),
+                ('r', true) => output.push('\r'),
+                ('f', true) => output.push('\x0C'),
+                ('v', true) => output.push('\x0B'),
+                ('b', true) => output.push('\x08'),
+                // a NUL cannot be in an environment variable
+                ('0', true) => return Err(err(ParseErrorKind::InvalidEscape, index - 1)),
+                ('x', true) => hex_escape = Some(HexEscape::new(index - 1, false)),
+                ('u', true) => hex_escape = Some(HexEscape::new(index - 1, true)),
                 _ => {
-                    return Err(ParseBufError::LineParse(input.to_owned(), index));
+                    return Err(err(ParseErrorKind::InvalidEscape, index));
                 }
             }
 
@@ -163,47 +572,99 @@ fn parse_value(
                 output.push(c);
             }
         } else if substitution_mode != SubstitutionMode::None {
-            if c.is_alphanumeric() {
-                substitution_name.push(c);
-            } else {
-                match substitution_mode {
-                    SubstitutionMode::None => unreachable!(),
-                    SubstitutionMode::Block => {
-                        if c == '{' && substitution_name.is_empty() {
-                            substitution_mode = SubstitutionMode::EscapedBlock;
-                        } else {
-                            apply_substitution(
-                                substitution_data,
-                                &std::mem::take(&mut substitution_name),
-                                &mut output,
-                            );
-                            if c == '$' {
-                                substitution_mode = if !strong_quote && !escaped {
-                                    SubstitutionMode::Block
-                                } else {
-                                    SubstitutionMode::None
-                                }
-                            } else {
-                                substitution_mode = SubstitutionMode::None;
-                                output.push(c);
-                            }
-                        }
+            match substitution_mode {
+                SubstitutionMode::None => unreachable!(),
+                SubstitutionMode::Block => {
+                    if c == '{' {
+                        substitution_mode = SubstitutionMode::EscapedBlock;
+                    } else {
+                        substitution_name.push(c);
                     }
-                    SubstitutionMode::EscapedBlock => {
-                        if c == '}' {
-                            substitution_mode = SubstitutionMode::None;
-                            apply_substitution(
-                                substitution_data,
-                                &std::mem::take(&mut substitution_name),
-                                &mut output,
-                            );
-                        } else {
-                            substitution_name.push(c);
+                }
+                SubstitutionMode::EscapedBlock => match (c, Operator::from_char(c)) {
+                    ('}', _) => {
+                        substitution_mode = SubstitutionMode::None;
+                        let name = std::mem::take(&mut substitution_name);
+                        if !in_unused_word(&expansions) {
+                            apply_substitution(lookup, &name, &mut output)?;
                         }
                     }
+                    (':', _) if !substitution_name.is_empty() => {
+                        substitution_mode = SubstitutionMode::Colon;
+                    }
+                    (_, Some(operator)) if !substitution_name.is_empty() => {
+                        substitution_mode = SubstitutionMode::None;
+                        push_expansion(
+                            lookup,
+                            &mut expansions,
+                            Expansion {
+                                name: std::mem::take(&mut substitution_name),
+                                operator,
+                                colon: false,
+                                dollar: substitution_start,
+                                start: output.len(),
+                                quoted: weak_quote,
+                                value: None,
+                                skipped: false,
+                            },
+                        )?;
+                    }
+                    _ => substitution_name.push(c),
+                },
+                SubstitutionMode::Colon => {
+                    let Some(operator) = Operator::from_char(c) else {
+                        return Err(err(ParseErrorKind::InvalidSubstitution, index));
+                    };
+                    substitution_mode = SubstitutionMode::None;
+                    push_expansion(
+                        lookup,
+                        &mut expansions,
+                        Expansion {
+                            name: std::mem::take(&mut substitution_name),
+                            operator,
+                            colon: true,
+                            dollar: substitution_start,
+                            start: output.len(),
+                            quoted: weak_quote,
+                            value: None,
+                            skipped: false,
+                        },
+                    )?;
                 }
             }
-        } else if c == '$' {
+        } else if c == '}'
+            && expansions
+                .last()
+                .is_some_and(|expansion| expansion.quoted == weak_quote)
+        {
+            let expansion = expansions.pop().unwrap();
+            let word = output.split_off(expansion.start);
+            if expansion.skipped {
+                continue;
+            }
+            match (expansion.operator, expansion.value) {
+                (Operator::Alternate, Some(_)) | (Operator::Default, None) => {
+                    output.push_str(&word);
+                }
+                (Operator::Alternate, None) => {}
+                (_, Some(value)) => output.push_str(&value),
+                (Operator::Required, None) => {
+                    let message = if !word.is_empty() {
+                        word
+                    } else if expansion.colon {
+                        "parameter null or not set".to_owned()
+                    } else {
+                        "parameter not set".to_owned()
+                    };
+                    return Err(ParseBufError::Required {
+                        name: expansion.name,
+                        message,
+                        line: start.advance(&input[..expansion.dollar]).line,
+                    });
+                }
+            }
+        } else if c == '$' && substitution {
+            substitution_start = index;
             substitution_mode = if !strong_quote && !escaped {
                 SubstitutionMode::Block
             } else {
@@ -219,11 +680,13 @@ fn parse_value(
             }
         } else if c == '\'' {
             strong_quote = true;
+            quote_start = index;
         } else if c == '"' {
             weak_quote = true;
+            quote_start = index;
         } else if c == '\\' {
             escaped = true;
-        } else if c == ' ' || c == '\t' {
+        } else if (c == ' ' || c == '\t') && expansions.is_empty() {
             expecting_end = true;
         } else {
             output.push(c);
@@ -231,46 +694,122 @@ fn parse_value(
     }
 
     //XXX also fail if escaped? or...
-    if substitution_mode == SubstitutionMode::EscapedBlock || strong_quote || weak_quote {
-        let value_length = input.len();
-        Err(ParseBufError::LineParse(
-            input.to_owned(),
-            if value_length == 0 {
-                0
-            } else {
-                value_length - 1
-            },
+    if strong_quote {
+        Err(err(ParseErrorKind::UnterminatedSingleQuote, quote_start))
+    } else if weak_quote {
+        Err(err(ParseErrorKind::UnterminatedDoubleQuote, quote_start))
+    } else if matches!(
+        substitution_mode,
+        SubstitutionMode::EscapedBlock | SubstitutionMode::Colon
+    ) {
+        Err(err(
+            ParseErrorKind::UnterminatedSubstitution,
+            substitution_start,
+        ))
+    } else if let Some(expansion) = expansions.last() {
+        Err(err(
+            ParseErrorKind::UnterminatedSubstitution,
+            expansion.dollar,
         ))
     } else {
-        apply_substitution(
-            substitution_data,
-            &std::mem::take(&mut substitution_name),
-            &mut output,
-        );
+        if substitution_mode == SubstitutionMode::Block {
+            apply_substitution(lookup, &substitution_name, &mut output)?;
+        }
         Ok(output)
     }
 }
 
+impl Operator {
+    const fn from_char(c: char) -> Option<Self> {
+        match c {
+            '-' => Some(Self::Default),
+            '?' => Some(Self::Required),
+            '+' => Some(Self::Alternate),
+            _ => None,
+        }
+    }
+}
+
+/// Looks up a variable in the substitution source, falling back to the values parsed so far.
+///
+/// Returns `None` if the variable is unset.
+fn lookup(
+    substitution_data: &HashMap<String, String>,
+    substitution_source: &dyn SubstitutionSource,
+    substitution_name: &str,
+) -> Option<String> {
+    substitution_source
+        .lookup(substitution_name)
+        .or_else(|| substitution_data.get(substitution_name).cloned())
+}
+
+/// Whether the innermost expansion has a word that is not used, such as the default of a set variable.
+///
+/// Like POSIX, such a word is not expanded, so its substitutions are not looked up and its `?` expansions do not fail.
+fn in_unused_word(expansions: &[Expansion]) -> bool {
+    expansions
+        .last()
+        .is_some_and(|expansion| !expansion.uses_word())
+}
+
+/// Starts an expansion whose word follows, looking up its variable unless it is inside an unused word.
+fn push_expansion(
+    lookup: &mut Lookup,
+    expansions: &mut Vec<Expansion>,
+    mut expansion: Expansion,
+) -> Result<(), ParseBufError> {
+    expansion.skipped = in_unused_word(expansions);
+    if !expansion.skipped {
+        expansion.value =
+            lookup(&expansion.name)?.filter(|value| !(expansion.colon && value.is_empty()));
+    }
+    expansions.push(expansion);
+    Ok(())
+}
+
 fn apply_substitution(
-    substitution_data: &HashMap<String, Option<String>>,
+    lookup: &mut Lookup,
     substitution_name: &str,
     output: &mut String,
-) {
-    if let Ok(environment_value) = env::var(substitution_name) {
-        output.push_str(&environment_value);
-    } else {
-        let stored_value = substitution_data
-            .get(substitution_name)
-            .unwrap_or(&None)
-            .to_owned();
-        output.push_str(&stored_value.unwrap_or_default());
-    };
+) -> Result<(), ParseBufError> {
+    output.push_str(&lookup(substitution_name)?.unwrap_or_default());
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use super::parse_include;
     use crate::iter::{Iter, ParseBufError};
 
+    #[test]
+    fn test_parse_include() {
+        let include = |line: &str| parse_include(line, line.split(" #").next().unwrap());
+        assert_eq!(
+            include("#include ../.env.base").as_deref(),
+            Some("../.env.base")
+        );
+        assert_eq!(
+            include("  source 'common file.env' # shared").as_deref(),
+            Some("common file.env")
+        );
+        assert_eq!(
+            include("source \"common.env\"").as_deref(),
+            Some("common.env")
+        );
+        assert_eq!(
+            include("#include ../shared/.env.base # shared").as_deref(),
+            Some("../shared/.env.base")
+        );
+        assert_eq!(include("#include 'a #b' # shared").as_deref(), Some("a #b"));
+        assert_eq!(include("#include a#b").as_deref(), Some("a#b"));
+        assert_eq!(include("#included by hand"), None);
+        assert_eq!(include("#include"), None);
+        assert_eq!(include("source=1"), None);
+        assert_eq!(include("source = 1"), None);
+        assert_eq!(include("sourced=1"), None);
+        assert_eq!(include("KEY=source x"), None);
+    }
+
     #[test]
     fn test_parse_line_env() -> Result<(), ParseBufError> {
         // Note 5 spaces after 'KEY8=' below
@@ -320,6 +859,168 @@ export   SHELL_LOVER=1
         Ok(())
     }
 
+    #[test]
+    fn test_parse_line_substitution_ends_at_quote() -> Result<(), ParseBufError> {
+        // the character ending a `$NAME` substitution is handled like any other, so it can close a quote
+        let actual_iter = Iter::new(
+            r#"
+SET=value
+KEY="$SET"
+KEY2=$SET'x'
+KEY3="$SET"x
+KEY4=$SET\ x
+"#
+            .as_bytes(),
+        );
+
+        let expected_iter = vec![
+            ("SET", "value"),
+            ("KEY", "value"),
+            ("KEY2", "valuex"),
+            ("KEY3", "valuex"),
+            ("KEY4", "value x"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()));
+
+        let mut count = 0;
+        for (expected, actual) in expected_iter.zip(actual_iter) {
+            assert_eq!(expected, actual?);
+            count += 1;
+        }
+        assert_eq!(count, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_line_default_value() -> Result<(), ParseBufError> {
+        let actual_iter = Iter::new(
+            r#"
+EMPTY=
+SET=value
+KEY=${UNSET:-8080}
+KEY2=${UNSET-8080}
+KEY3=${EMPTY:-default}
+KEY4=${EMPTY-default}
+KEY5=${SET:-default}
+KEY6="${UNSET:-with spaces}"
+KEY7=${UNSET:-$SET}
+KEY8=${UNSET:-${EMPTY:-${SET}}}
+KEY9=${UNSET:-'$SET'}
+KEY10=${UNSET:-{\}}
+KEY11=${UNSET:-}
+KEY12=${SET:-${UNSET:?boom}}
+KEY13=${UNSET:-"}"}
+"#
+            .as_bytes(),
+        );
+
+        let expected_iter = vec![
+            ("EMPTY", ""),
+            ("SET", "value"),
+            ("KEY", "8080"),
+            ("KEY2", "8080"),
+            ("KEY3", "default"),
+            ("KEY4", ""),
+            ("KEY5", "value"),
+            ("KEY6", "with spaces"),
+            ("KEY7", "value"),
+            ("KEY8", "value"),
+            ("KEY9", "$SET"),
+            ("KEY10", "{}"),
+            ("KEY11", ""),
+            ("KEY12", "value"),
+            ("KEY13", "}"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()));
+
+        let mut count = 0;
+        for (expected, actual) in expected_iter.zip(actual_iter) {
+            assert_eq!(expected, actual?);
+            count += 1;
+        }
+        assert_eq!(count, 15);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_line_alternate_value() -> Result<(), ParseBufError> {
+        let actual_iter = Iter::new(
+            r#"
+EMPTY=
+SET=value
+KEY=${SET:+--verbose}
+KEY2=${SET+--verbose}
+KEY3=${EMPTY:+alternate}
+KEY4=${EMPTY+alternate}
+KEY5=${UNSET:+alternate}
+KEY6=${UNSET+alternate}
+KEY7="${SET:+with $SET}"
+KEY8=${SET:+${UNSET:-nested}}
+KEY9=${UNSET:+${UNSET2:?boom}}
+"#
+            .as_bytes(),
+        );
+
+        let expected_iter = vec![
+            ("EMPTY", ""),
+            ("SET", "value"),
+            ("KEY", "--verbose"),
+            ("KEY2", "--verbose"),
+            ("KEY3", ""),
+            ("KEY4", "alternate"),
+            ("KEY5", ""),
+            ("KEY6", ""),
+            ("KEY7", "with value"),
+            ("KEY8", "nested"),
+            ("KEY9", ""),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()));
+
+        let mut count = 0;
+        for (expected, actual) in expected_iter.zip(actual_iter) {
+            assert_eq!(expected, actual?);
+            count += 1;
+        }
+        assert_eq!(count, 11);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_line_without_substitution() -> Result<(), ParseBufError> {
+        let actual_iter = Iter::new(
+            r#"
+KEY=value
+KEY2=$KEY
+KEY3="$2b$12$R9h/cIPz0gi.URNNX3kh2O"
+KEY4=${KEY:-default}
+KEY5=\$KEY
+"#
+            .as_bytes(),
+        )
+        .substitution(false);
+
+        let expected_iter = vec![
+            ("KEY", "value"),
+            ("KEY2", "$KEY"),
+            ("KEY3", "$2b$12$R9h/cIPz0gi.URNNX3kh2O"),
+            ("KEY4", "${KEY:-default}"),
+            ("KEY5", "$KEY"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()));
+
+        let mut count = 0;
+        for (expected, actual) in expected_iter.zip(actual_iter) {
+            assert_eq!(expected, actual?);
+            count += 1;
+        }
+        assert_eq!(count, 5);
+        Ok(())
+    }
+
     #[test]
     fn test_parse_line_comment() {
         let input = br"
@@ -382,6 +1083,41 @@ KEY7="line 1\nline 2"
         Ok(())
     }
 
+    #[test]
+    fn test_parse_value_c_escapes() -> Result<(), ParseBufError> {
+        let actual_iter = Iter::new(
+            r#"
+KEY="a\tb\rc\fd\ve\bf"
+KEY2="\x41\x7e\xe9"
+KEY3="\u00e9\u20AC"
+KEY4="\u{1F600}\u{41}"
+KEY5="\\t"
+KEY6='\t'
+"#
+            .as_bytes(),
+        );
+
+        let vec = vec![
+            ("KEY", "a\tb\rc\x0Cd\x0Be\x08f"),
+            ("KEY2", "A~\u{e9}"),
+            ("KEY3", "\u{e9}\u{20ac}"),
+            ("KEY4", "\u{1F600}A"),
+            ("KEY5", r"\t"),
+            ("KEY6", r"\t"),
+        ];
+        let expected_iter = vec
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()));
+
+        let mut count = 0;
+        for (expected, actual) in expected_iter.zip(actual_iter) {
+            assert_eq!(expected, actual?);
+            count += 1;
+        }
+        assert_eq!(count, 6);
+        Ok(())
+    }
+
     #[test]
     fn test_parse_value_escapes_invalid() {
         let actual_iter = Iter::new(
@@ -433,6 +1169,17 @@ mod substitution_tests {
         )
     }
 
+    #[test]
+    fn variable_without_parenthesis_surrounded_by_quotes() -> Result<(), ParseBufError> {
+        assert_str(
+            r#"
+            KEY=test
+            KEY1="$KEY"
+            "#,
+            vec![("KEY", "test"), ("KEY1", "test")],
+        )
+    }
+
     #[test]
     fn sub_undefined_variables_to_empty_string() -> Result<(), ParseBufError> {
         assert_str(r#"KEY=">$KEY1<>${KEY2}<""#, vec![("KEY", "><><")])
@@ -556,9 +1303,112 @@ mod substitution_tests {
     }
 }
 
+#[cfg(test)]
+mod resolver_tests {
+    use super::{RawEntry, Resolver};
+    use crate::{iter::ParseBufError, FileOnly, Position};
+
+    fn resolver(input: &[(&str, &str)]) -> Resolver<'static> {
+        let mut resolver = Resolver::new(&FileOnly, true);
+        for (line_number, (key, value)) in input.iter().enumerate() {
+            let position = Position::start_of_line(line_number + 1);
+            resolver.insert(
+                RawEntry {
+                    key: (*key).to_owned(),
+                    value,
+                    position,
+                    value_position: position.advance(key).advance("="),
+                    key_offset: 0,
+                    value_offset: key.len() + 1,
+                },
+                Vec::new(),
+            );
+        }
+        resolver
+    }
+
+    #[test]
+    fn forward_reference() -> Result<(), ParseBufError> {
+        let mut resolver = resolver(&[("KEY", "${KEY1}+KEY_VALUE"), ("KEY1", "KEY1_VALUE")]);
+        assert_eq!(resolver.resolve("KEY")?, "KEY1_VALUE+KEY_VALUE");
+        Ok(())
+    }
+
+    #[test]
+    fn self_reference_is_unset() -> Result<(), ParseBufError> {
+        let mut resolver = resolver(&[("KEY", "${KEY:-default}")]);
+        assert_eq!(resolver.resolve("KEY")?, "default");
+        Ok(())
+    }
+
+    #[test]
+    fn unused_default_is_not_a_cycle() -> Result<(), ParseBufError> {
+        let mut resolver = resolver(&[("KEY", "${KEY1:+x}"), ("KEY1", "${KEY2-y}")]);
+        assert_eq!(resolver.resolve("KEY")?, "x");
+        Ok(())
+    }
+
+    #[test]
+    fn cycle() {
+        let mut resolver = resolver(&[("KEY", "$KEY1"), ("KEY1", "${KEY}")]);
+        assert!(matches!(
+            resolver.resolve("KEY"),
+            Err(ParseBufError::SubstitutionCycle(ref cycle)) if cycle == &["KEY", "KEY1", "KEY"]
+        ));
+    }
+
+    #[test]
+    fn lenient_reports_each_error_once() {
+        let resolver = resolver(&[
+            ("A", "${B}a"),
+            ("B", "${A}b"),
+            ("C", "${D:?required}"),
+            ("E", "${C:-e}"),
+            ("F", "f"),
+        ]);
+        let keys = ["A", "B", "C", "E", "F"].map(str::to_owned).to_vec();
+        let (entries, errors) = resolver.resolve_lenient(keys);
+
+        let entries = entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, [("B", "b"), ("E", "e"), ("F", "f")]);
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            ParseBufError::SubstitutionCycle(ref cycle) if cycle == &["A", "B", "A"]
+        ));
+        assert!(matches!(
+            errors[1],
+            ParseBufError::Required { ref name, line: 3, .. } if name == "D"
+        ));
+    }
+}
+
 #[cfg(test)]
 mod error_tests {
-    use crate::iter::{Iter, ParseBufError};
+    use crate::{
+        iter::{Iter, ParseBufError},
+        ParseErrorKind,
+    };
+
+    /// Asserts the result is a parse error of the kind at the line and column.
+    fn assert_parse_err(
+        result: &Result<(String, String), ParseBufError>,
+        expected_kind: ParseErrorKind,
+        expected_line: usize,
+        expected_column: usize,
+    ) {
+        assert!(
+            matches!(
+                result,
+                Err(ParseBufError::LineParse { kind, line, column })
+                    if *kind == expected_kind && *line == expected_line && *column == expected_column
+            ),
+            "expected {expected_kind:?} at {expected_line}:{expected_column}, got {result:?}"
+        );
+    }
 
     #[test]
     fn should_not_parse_unfinished_subs() {
@@ -566,10 +1416,10 @@ mod error_tests {
 
         let iter = Iter::new(
             format!(
-                r#"
+                r"
     FOO=bar
     BAR={invalid_value}
-    "#
+    "
             )
             .as_bytes(),
         )
@@ -581,9 +1431,100 @@ mod error_tests {
             &("FOO".to_owned(), "bar".to_owned())
         );
         // second line error
+        assert_parse_err(&iter[1], ParseErrorKind::UnterminatedSubstitution, 3, 10);
+    }
+
+    #[test]
+    fn should_not_parse_unfinished_default_value() {
+        let input = "VALUE=${KEY:-default";
+        let iter = Iter::new(input.as_bytes()).collect::<Vec<_>>();
+        assert_parse_err(&iter[0], ParseErrorKind::UnterminatedSubstitution, 1, 7);
+    }
+
+    #[test]
+    fn should_not_parse_unknown_operator() {
+        let input = "VALUE=${KEY:=default}";
+        let iter = Iter::new(input.as_bytes()).collect::<Vec<_>>();
+        assert_parse_err(&iter[0], ParseErrorKind::InvalidSubstitution, 1, 13);
+    }
+
+    #[test]
+    fn should_report_opening_quote_of_unterminated_value() {
+        let iter = Iter::new(
+            r#"
+FOO=bar
+MULTI="first
+second"
+  BAR=  'unterminated
+FOO=baz
+"#
+            .as_bytes(),
+        )
+        .collect::<Vec<_>>();
+
+        assert_eq!(iter.len(), 3);
+        assert_parse_err(&iter[2], ParseErrorKind::UnterminatedSingleQuote, 5, 9);
+    }
+
+    #[test]
+    fn should_count_lines_of_multiline_values() {
+        let iter = Iter::new(
+            r#"
+MULTI="first
+second
+third"
+# comment
+BAD=a b
+MULTI2="first
+  \q"
+"#
+            .as_bytes(),
+        )
+        .collect::<Vec<_>>();
+
+        assert_parse_err(&iter[1], ParseErrorKind::UnexpectedCharacter, 6, 7);
+        assert_parse_err(&iter[2], ParseErrorKind::InvalidEscape, 8, 4);
+    }
+
+    #[test]
+    fn should_not_parse_missing_equals() {
+        let input = "KEY value";
+        let iter = Iter::new(input.as_bytes()).collect::<Vec<_>>();
+        assert_parse_err(&iter[0], ParseErrorKind::MissingEquals, 1, 5);
+    }
+
+    #[test]
+    fn should_not_parse_unset_required_variable() {
+        let iter = Iter::new(
+            r#"
+SET=value
+EMPTY=
+KEY=${SET:?must be set}
+KEY2=${EMPTY?must be set}
+KEY3="${UNSET:?UNSET must be set}"
+KEY4=${EMPTY:?}
+KEY5=${UNSET?}
+"#
+            .as_bytes(),
+        )
+        .collect::<Vec<_>>();
+
+        assert_eq!(iter[2].as_ref().unwrap().1, "value");
+        assert_eq!(iter[3].as_ref().unwrap().1, "");
+        assert!(matches!(
+            iter[4],
+            Err(ParseBufError::Required { ref name, ref message, line })
+                if name == "UNSET" && message == "UNSET must be set" && line == 6
+        ));
         assert!(matches!(
-            iter[1],
-            Err(ParseBufError::LineParse(ref v, idx)) if v == invalid_value && idx == invalid_value.len() - 1
+            iter[5],
+            Err(ParseBufError::Required { ref name, ref message, line })
+                if name == "EMPTY" && message == "parameter null or not set" && line == 7
+        ));
+        assert!(matches!(
+            iter[6],
+            Err(ParseBufError::Required { ref name, ref message, line })
+                if name == "UNSET" && message == "parameter not set" && line == 8
         ));
     }
 
@@ -593,10 +1534,7 @@ mod error_tests {
 
         let iter = Iter::new(invalid_key.as_bytes()).collect::<Vec<_>>();
 
-        assert!(matches!(
-            iter[0],
-            Err(ParseBufError::LineParse(ref v, idx)) if v == invalid_key && idx == 0
-        ));
+        assert_parse_err(&iter[0], ParseErrorKind::InvalidKey, 1, 1);
     }
 
     #[test]
@@ -604,10 +1542,39 @@ mod error_tests {
         let invalid_fmt = r"<><><>";
         let iter = Iter::new(invalid_fmt.as_bytes()).collect::<Vec<_>>();
 
-        assert!(matches!(
-            iter[0],
-            Err(ParseBufError::LineParse(ref v, idx)) if v == invalid_fmt && idx == 0
-        ));
+        assert_parse_err(&iter[0], ParseErrorKind::InvalidKey, 1, 1);
+    }
+
+    #[test]
+    fn should_not_parse_malformed_c_escapes() {
+        // (value, index of the error in the value)
+        let cases = [
+            (r#""\xG1""#, 3),
+            (r#""\x4""#, 4),
+            (r#""\u12""#, 5),
+            (r#""\u{}""#, 4),
+            (r#""\u{1234567}""#, 10),
+            (r#""\uD800""#, 1),
+            (r#""ok \u{110000}""#, 4),
+            (r#""\q""#, 2),
+            (r"\t", 1),
+        ];
+        for (invalid_esc, index) in cases {
+            let iter = Iter::new(format!("VALUE={invalid_esc}").as_bytes()).collect::<Vec<_>>();
+
+            assert_parse_err(&iter[0], ParseErrorKind::InvalidEscape, 1, 7 + index);
+        }
+    }
+
+    #[test]
+    fn should_not_parse_nul_escapes() {
+        // (value, index of the backslash in the value)
+        let cases = [(r#""a\0b""#, 2), (r#""\x00""#, 1), (r#""\u{0}""#, 1)];
+        for (nul_esc, index) in cases {
+            let iter = Iter::new(format!("VALUE={nul_esc}").as_bytes()).collect::<Vec<_>>();
+
+            assert_parse_err(&iter[0], ParseErrorKind::InvalidEscape, 1, 7 + index);
+        }
     }
 
     #[test]
@@ -615,9 +1582,6 @@ mod error_tests {
         let invalid_esc = r">\f<";
         let iter = Iter::new(format!("VALUE={invalid_esc}").as_bytes()).collect::<Vec<_>>();
 
-        assert!(matches!(
-            iter[0],
-            Err(ParseBufError::LineParse(ref v, idx)) if v == invalid_esc && idx == invalid_esc.find('\\').unwrap() + 1
-        ));
+        assert_parse_err(&iter[0], ParseErrorKind::InvalidEscape, 1, 9);
     }
 }
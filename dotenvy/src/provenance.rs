@@ -0,0 +1,115 @@
+use std::{collections::HashMap, fmt, ops::Deref, path::PathBuf};
+
+/// Where the value of a variable came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Origin {
+    /// A definition in the input. `path` is `None` when the input is a reader without a path.
+    Input { path: Option<PathBuf>, line: usize },
+    /// The environment of the current process.
+    ProcessEnv,
+    /// A fallback used when the variable is not set elsewhere.
+    Default,
+    /// A value set by the program.
+    Programmatic,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Input {
+                path: Some(path),
+                line,
+            } => write!(f, "{}:{line}", path.display()),
+            Self::Input { path: None, line } => write!(f, "line {line}"),
+            Self::ProcessEnv => f.write_str("process environment"),
+            Self::Default => f.write_str("default"),
+            Self::Programmatic => f.write_str("program"),
+        }
+    }
+}
+
+/// The origin of the value of a variable, and the values it replaced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    /// The origin of the effective value.
+    pub origin: Origin,
+    /// The values that were replaced by the effective value, oldest first.
+    pub shadowed: Vec<Shadowed>,
+}
+
+/// A value that was replaced by a later one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shadowed {
+    pub origin: Origin,
+    pub value: String,
+}
+
+/// The provenance of each variable in an [`EnvMap`](crate::EnvMap).
+///
+/// This is created by [`EnvLoader::load_with_provenance`](crate::EnvLoader::load_with_provenance).
+/// It dereferences to a `HashMap` from each key to its [`Provenance`].
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct ProvenanceMap(HashMap<String, Provenance>);
+
+impl Deref for ProvenanceMap {
+    type Target = HashMap<String, Provenance>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl ProvenanceMap {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the value of the key now comes from the origin.
+    ///
+    /// `previous` is the value it replaced, as returned by [`EnvMap::insert`](crate::EnvMap::insert),
+    /// which is recorded as shadowed with its origin. It is ignored if the key has no recorded origin.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::{EnvMap, Origin, ProvenanceMap};
+    ///
+    /// let mut env_map = EnvMap::new();
+    /// let mut provenance = ProvenanceMap::new();
+    /// for (value, origin) in [("info", Origin::Default), ("debug", Origin::Programmatic)] {
+    ///     let previous = env_map.insert("LOG_LEVEL".to_owned(), value.to_owned());
+    ///     provenance.record("LOG_LEVEL", origin, previous);
+    /// }
+    ///
+    /// let log_level = &provenance["LOG_LEVEL"];
+    /// assert_eq!(log_level.origin, Origin::Programmatic);
+    /// assert_eq!(log_level.shadowed[0].origin, Origin::Default);
+    /// assert_eq!(log_level.shadowed[0].value, "info");
+    /// ```
+    pub fn record(&mut self, key: &str, origin: Origin, previous: Option<String>) {
+        match (self.0.get_mut(key), previous) {
+            (Some(provenance), Some(value)) => {
+                let origin = std::mem::replace(&mut provenance.origin, origin);
+                provenance.shadowed.push(Shadowed { origin, value });
+            }
+            (Some(provenance), None) => provenance.origin = origin,
+            (None, _) => {
+                self.0.insert(
+                    key.to_owned(),
+                    Provenance {
+                        origin,
+                        shadowed: Vec::new(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Returns the origin of the value of the key.
+    #[must_use]
+    pub fn origin(&self, key: &str) -> Option<&Origin> {
+        self.0.get(key).map(|provenance| &provenance.origin)
+    }
+}
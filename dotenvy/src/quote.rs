@@ -0,0 +1,111 @@
+use std::{borrow::Cow, fmt::Write};
+
+/// Quotes and escapes a value so that it parses back to the same value.
+///
+/// Values that need no quoting are left as they are.
+/// Otherwise, single quotes are preferred as their contents are literal, and double quotes with escapes are used for the rest.
+/// A NUL character is escaped as `\u{0}`, which fails to parse, as no environment variable can hold it.
+pub fn quote(value: &str) -> Cow<'_, str> {
+    if is_bare(value) {
+        return Cow::Borrowed(value);
+    }
+    // backslashes are literal in single quotes, but would still escape the closing quote when splitting lines
+    if !value.contains(['\'', '\\']) && !value.contains(char::is_control) {
+        return Cow::Owned(format!("'{value}'"));
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' | '"' | '$' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{{{:x}}}", u32::from(c));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+/// Returns whether the value can be written without quotes.
+fn is_bare(value: &str) -> bool {
+    !value.starts_with('#')
+        && value.chars().all(|c| {
+            c.is_alphanumeric()
+                || matches!(
+                    c,
+                    '_' | '-'
+                        | '.'
+                        | '/'
+                        | ':'
+                        | ','
+                        | '@'
+                        | '+'
+                        | '%'
+                        | '='
+                        | '^'
+                        | '~'
+                        | '*'
+                        | '!'
+                        | '?'
+                        | '#'
+                        | '['
+                        | ']'
+                        | '('
+                        | ')'
+                        | '<'
+                        | '>'
+                        | ';'
+                        | '&'
+                        | '|'
+                        | '{'
+                        | '}'
+                )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::quote;
+    use crate::{EnvLoader, EnvSequence};
+    use std::io::Cursor;
+
+    #[test]
+    fn round_trip() -> Result<(), crate::Error> {
+        let values = [
+            ("", ""),
+            ("plain", "plain"),
+            (
+                "postgres://user@host:5432/db?ssl=true",
+                "postgres://user@host:5432/db?ssl=true",
+            ),
+            ("a#b", "a#b"),
+            ("#a", "'#a'"),
+            ("two words", "'two words'"),
+            ("$HOME", "'$HOME'"),
+            ("it's", "\"it's\""),
+            ("back\\slash", "\"back\\\\slash\""),
+            ("multi\nline", "\"multi\\nline\""),
+            ("tab\tand \"quote\" $x", "\"tab\\tand \\\"quote\\\" \\$x\""),
+            ("bell\u{7}", "\"bell\\u{7}\""),
+            ("ünïcödé", "ünïcödé"),
+        ];
+        for (value, expected) in values {
+            let quoted = quote(value);
+            assert_eq!(quoted, expected);
+
+            let env_map = EnvLoader::with_reader(Cursor::new(format!("KEY={quoted}")))
+                .sequence(EnvSequence::InputOnly)
+                .load()?;
+            assert_eq!(env_map.var("KEY")?, value);
+        }
+        Ok(())
+    }
+}
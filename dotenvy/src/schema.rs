@@ -0,0 +1,718 @@
+use crate::{
+    typed, validate::IssueKind, Document, EnvMap, Error, Node, Origin, Policy, ProvenanceMap,
+    ValidationReport,
+};
+use regex::Regex;
+use std::{cmp::Ordering, fmt, fs, mem, path::Path, str::FromStr};
+
+/// The type of the value of a variable in a [`Schema`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValueType {
+    /// Any value. This is the default.
+    String,
+    /// A whole number, such as `-5`.
+    Int,
+    /// A number, such as `0.5`.
+    Float,
+    /// A boolean, as parsed by [`EnvMap::parse_bool`].
+    Bool,
+    /// A TCP or UDP port, from 1 to 65535.
+    Port,
+    /// A duration, as parsed by [`EnvMap::parse_duration`].
+    Duration,
+}
+
+impl FromStr for ValueType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(Self::String),
+            "int" => Ok(Self::Int),
+            "float" => Ok(Self::Float),
+            "bool" => Ok(Self::Bool),
+            "port" => Ok(Self::Port),
+            "duration" => Ok(Self::Duration),
+            _ => Err(format!(
+                "unknown type '{s}', expected string, int, float, bool, port or duration"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::String => "string",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Bool => "bool",
+            Self::Port => "port",
+            Self::Duration => "duration",
+        })
+    }
+}
+
+/// The constraints on one variable in a [`Schema`].
+#[derive(Debug, Clone)]
+pub struct KeySchema {
+    key: String,
+    origin: Origin,
+    value_type: ValueType,
+    required: bool,
+    default: Option<String>,
+    allowed: Vec<String>,
+    pattern: Option<(String, Regex)>,
+    min: Option<Bound>,
+    max: Option<Bound>,
+}
+
+/// A bound of `@min` or `@max`, which is also kept as an integer so that `int` values are compared exactly.
+#[derive(Debug, Clone, Copy)]
+struct Bound {
+    number: f64,
+    integer: Option<i64>,
+}
+
+impl fmt::Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.integer {
+            Some(integer) => write!(f, "{integer}"),
+            None => write!(f, "{}", self.number),
+        }
+    }
+}
+
+/// A value of an `int`, `float` or `port`, to be compared with the bounds.
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    /// Compares with a bound, which is an integer for an `int` or `port` as checked by `KeySchema::finish`.
+    fn compare(self, bound: Bound) -> Option<Ordering> {
+        match (self, bound.integer) {
+            (Self::Integer(number), Some(bound)) => Some(number.cmp(&bound)),
+            (Self::Integer(_), None) => None,
+            (Self::Float(number), _) => number.partial_cmp(&bound.number),
+        }
+    }
+}
+
+impl KeySchema {
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The line of the schema on which the key is declared.
+    #[must_use]
+    pub const fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    #[must_use]
+    pub const fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    #[must_use]
+    pub const fn is_required(&self) -> bool {
+        self.required
+    }
+
+    #[must_use]
+    pub fn default(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    /// The values allowed by `@enum`. Any value is allowed if this is empty.
+    #[must_use]
+    pub fn allowed(&self) -> &[String] {
+        &self.allowed
+    }
+
+    /// The regular expression of `@pattern`.
+    #[must_use]
+    pub fn pattern(&self) -> Option<&str> {
+        self.pattern.as_ref().map(|(pattern, _)| pattern.as_str())
+    }
+
+    #[must_use]
+    pub fn min(&self) -> Option<f64> {
+        self.min.map(|bound| bound.number)
+    }
+
+    #[must_use]
+    pub fn max(&self) -> Option<f64> {
+        self.max.map(|bound| bound.number)
+    }
+
+    fn new(key: &str, origin: Origin) -> Self {
+        Self {
+            key: key.to_owned(),
+            origin,
+            value_type: ValueType::String,
+            required: false,
+            default: None,
+            allowed: Vec::new(),
+            pattern: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    fn annotate(&mut self, name: &str, value: Option<String>) -> Result<(), String> {
+        let value = |name| value.ok_or_else(|| format!("@{name} requires a value"));
+        match name {
+            "type" => self.value_type = value(name)?.parse()?,
+            "required" => self.required = true,
+            "default" => self.default = Some(value(name)?),
+            "enum" => {
+                self.allowed = value(name)?
+                    .split(',')
+                    .map(|item| item.trim().to_owned())
+                    .collect();
+            }
+            "pattern" => {
+                let pattern = value(name)?;
+                let regex = Regex::new(&format!("^(?:{pattern})$"))
+                    .map_err(|e| format!("invalid pattern: {e}"))?;
+                self.pattern = Some((pattern, regex));
+            }
+            "min" | "max" => {
+                let text = value(name)?;
+                let number = text
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|number| number.is_finite())
+                    .ok_or_else(|| format!("@{name} expects a number, found '{text}'"))?;
+                let bound = Bound {
+                    number,
+                    integer: text.parse().ok(),
+                };
+                if name == "min" {
+                    self.min = Some(bound);
+                } else {
+                    self.max = Some(bound);
+                }
+            }
+            _ => return Err(format!("unknown annotation '@{name}'")),
+        }
+        Ok(())
+    }
+
+    /// Checks the annotations of the key once they have all been read.
+    fn finish(&self) -> Result<(), String> {
+        let numeric = matches!(
+            self.value_type,
+            ValueType::Int | ValueType::Float | ValueType::Port
+        );
+        if (self.min.is_some() || self.max.is_some()) && !numeric {
+            return Err(format!(
+                "@min and @max require a type of int, float or port, not {}",
+                self.value_type
+            ));
+        }
+        let integral = matches!(self.value_type, ValueType::Int | ValueType::Port);
+        if integral
+            && [self.min, self.max]
+                .iter()
+                .flatten()
+                .any(|bound| bound.integer.is_none())
+        {
+            return Err(format!(
+                "@min and @max must be integers for a type of {}",
+                self.value_type
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks a value, returning the reason it is invalid.
+    fn check(&self, value: &str) -> Result<(), String> {
+        let number = match self.value_type {
+            ValueType::String => None,
+            ValueType::Int => Some(Number::Integer(
+                value
+                    .parse()
+                    .map_err(|_| "expected an integer".to_owned())?,
+            )),
+            // `NaN` and `inf` parse, but cannot be compared with the bounds
+            ValueType::Float => Some(Number::Float(
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|number| number.is_finite())
+                    .ok_or_else(|| "expected a finite number".to_owned())?,
+            )),
+            ValueType::Bool => {
+                typed::parse_bool(value)?;
+                None
+            }
+            ValueType::Port => {
+                let port = value
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|&port| port != 0)
+                    .ok_or_else(|| "expected a port from 1 to 65535".to_owned())?;
+                Some(Number::Integer(i64::from(port)))
+            }
+            ValueType::Duration => {
+                typed::parse_duration(value)?;
+                None
+            }
+        };
+        if !self.allowed.is_empty() && !self.allowed.iter().any(|allowed| allowed == value) {
+            return Err(format!("expected one of {}", self.allowed.join(", ")));
+        }
+        if let Some((pattern, regex)) = &self.pattern {
+            if !regex.is_match(value) {
+                return Err(format!("expected a match for the pattern {pattern}"));
+            }
+        }
+        if let Some(number) = number {
+            if let Some(min) = self
+                .min
+                .filter(|&min| number.compare(min) == Some(Ordering::Less))
+            {
+                return Err(format!("expected at least {min}"));
+            }
+            if let Some(max) = self
+                .max
+                .filter(|&max| number.compare(max) == Some(Ordering::Greater))
+            {
+                return Err(format!("expected at most {max}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Types and constraints for variables, declared with annotations in the comments above each key of an env file.
+///
+/// Annotations start with `@` and are separated by whitespace. A value containing whitespace can be written in double quotes.
+/// A comment is only read as annotations if it starts with one of those below, so other comments such as `# @see docs` are ignored.
+///
+/// - `@type=NAME` is one of `string`, `int`, `float`, `bool`, `port` and `duration`. The default is `string`.
+/// - `@required` fails if the variable is not set or empty.
+/// - `@default=VALUE` is used if the variable is not set or empty.
+/// - `@enum=a,b,c` allows only the listed values.
+/// - `@pattern=REGEX` requires the entire value to match the regular expression.
+/// - `@min=N` and `@max=N` bound the value of an `int`, `float` or `port`. They must be integers for an `int` or `port`.
+///
+/// The values of the keys in the schema are ignored, so an example file such as `.env.example` can be used as the schema.
+/// A variable that is empty and not required is not checked. Requires the `schema` feature.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{EnvLoader, EnvSequence, Schema};
+/// use std::io::Cursor;
+///
+/// let schema = Schema::parse(concat!(
+///     "# @type=port @default=8080\n",
+///     "PORT=\n",
+///     "# Log verbosity\n",
+///     "# @enum=debug,info,warn @required\n",
+///     "LOG_LEVEL=info\n",
+/// ))?;
+///
+/// let env_map = EnvLoader::with_reader(Cursor::new("LOG_LEVEL=debug"))
+///     .sequence(EnvSequence::InputOnly)
+///     .load_with_schema(&schema)?;
+/// assert_eq!(env_map.var("PORT")?, "8080");
+///
+/// let err = EnvLoader::with_reader(Cursor::new("PORT=http\nLOG_LEVEL=trace"))
+///     .path(".env")
+///     .sequence(EnvSequence::InputOnly)
+///     .load_with_schema(&schema)
+///     .unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     ".env:1: PORT has an invalid value 'http': expected a port from 1 to 65535; \
+///      .env:2: LOG_LEVEL has an invalid value 'trace': expected one of debug, info, warn"
+/// );
+/// # Ok::<(), dotenvy::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    keys: Vec<KeySchema>,
+}
+
+impl Schema {
+    /// Reads a schema from a file.
+    ///
+    /// Fails with `Error::Schema` if an annotation is invalid, with the path and line of the annotation.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let input = fs::read_to_string(path).map_err(|e| Error::Io(e, Some(path.to_owned())))?;
+        Self::parse_with_path(&input, Some(path))
+    }
+
+    /// Parses a schema from the contents of an env file.
+    ///
+    /// Fails with `Error::Schema` if an annotation is invalid, with the line of the annotation.
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        Self::parse_with_path(input, None)
+    }
+
+    /// Returns the keys of the schema, in the order they are declared.
+    pub fn keys(&self) -> impl Iterator<Item = &KeySchema> {
+        self.keys.iter()
+    }
+
+    /// Sets each variable in the schema that is not set or empty to its default, if it has one.
+    pub fn apply_defaults(&self, env_map: &mut EnvMap) {
+        self.apply_defaults_with_provenance(env_map, None);
+    }
+
+    /// Checks the variables against the schema, reporting every violation as an error.
+    ///
+    /// A required variable that is not set or empty is `IssueKind::Missing` or `IssueKind::Empty`
+    /// with the origin of its declaration in the schema, and an invalid value is `IssueKind::Invalid`.
+    /// Defaults are not applied, so [`apply_defaults`](Self::apply_defaults) should usually be called first.
+    #[must_use]
+    pub fn validate(&self, env_map: &EnvMap) -> ValidationReport {
+        self.validate_with_provenance(env_map, None)
+    }
+
+    pub(crate) fn apply_defaults_with_provenance(
+        &self,
+        env_map: &mut EnvMap,
+        mut provenance: Option<&mut ProvenanceMap>,
+    ) {
+        for key in &self.keys {
+            let Some(default) = &key.default else {
+                continue;
+            };
+            if env_map.get(&key.key).is_some_and(|value| !value.is_empty()) {
+                continue;
+            }
+            let previous = env_map.insert(key.key.clone(), default.clone());
+            if let Some(provenance) = provenance.as_deref_mut() {
+                provenance.record(&key.key, Origin::Default, previous);
+            }
+        }
+    }
+
+    pub(crate) fn validate_with_provenance(
+        &self,
+        env_map: &EnvMap,
+        provenance: Option<&ProvenanceMap>,
+    ) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        for key in &self.keys {
+            let origin = provenance.and_then(|provenance| provenance.origin(&key.key));
+            match env_map.get(&key.key) {
+                None if key.required => {
+                    report.push(
+                        Policy::Error,
+                        &key.key,
+                        IssueKind::Missing,
+                        Some(&key.origin),
+                    );
+                }
+                Some(value) if value.is_empty() && key.required => {
+                    report.push(Policy::Error, &key.key, IssueKind::Empty, origin);
+                }
+                Some(value) if !value.is_empty() => {
+                    if let Err(message) = key.check(value) {
+                        let kind = IssueKind::Invalid {
+                            value: value.clone(),
+                            message,
+                        };
+                        report.push(Policy::Error, &key.key, kind, origin);
+                    }
+                }
+                _ => {}
+            }
+        }
+        report
+    }
+
+    fn parse_with_path(input: &str, path: Option<&Path>) -> Result<Self, Error> {
+        let path = path.map(Path::to_path_buf);
+        let error = |message, line| Error::Schema {
+            message,
+            path: path.clone(),
+            line,
+        };
+        let mut keys = Vec::new();
+        let mut annotations = Vec::new();
+        let mut line = 1;
+        for node in Document::parse(input).nodes() {
+            match node {
+                Node::Comment(text) => {
+                    let text = text.trim_start().trim_start_matches('#').trim();
+                    if is_annotation(text) {
+                        annotations.push((line, text.to_owned()));
+                    }
+                }
+                Node::Entry(entry) => {
+                    let origin = Origin::Input {
+                        path: path.clone(),
+                        line,
+                    };
+                    let mut key = KeySchema::new(entry.key(), origin);
+                    // `finish` only checks the bounds, so its errors are reported on the line of the first one
+                    let mut bounds_line = None;
+                    for (line, text) in mem::take(&mut annotations) {
+                        for (name, value) in parse_annotations(&text).map_err(|e| error(e, line))? {
+                            if name == "min" || name == "max" {
+                                bounds_line.get_or_insert(line);
+                            }
+                            key.annotate(&name, value).map_err(|e| error(e, line))?;
+                        }
+                    }
+                    key.finish()
+                        .map_err(|e| error(e, bounds_line.unwrap_or(line)))?;
+                    keys.push(key);
+                }
+                Node::Blank(_) => annotations.clear(),
+                Node::Invalid(_) => {
+                    return Err(error("expected a key or a comment".to_owned(), line));
+                }
+            }
+            line += node.to_string().matches('\n').count();
+        }
+        Ok(Self { keys })
+    }
+}
+
+/// The names of the annotations, which must start a comment for it to be read as annotations.
+const ANNOTATIONS: [&str; 7] = [
+    "type", "required", "default", "enum", "pattern", "min", "max",
+];
+
+/// Returns whether a comment starts with a known annotation, so that comments such as `@see docs` are ignored.
+fn is_annotation(text: &str) -> bool {
+    text.strip_prefix('@').is_some_and(|annotation| {
+        let name_end = annotation
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(annotation.len());
+        ANNOTATIONS.contains(&&annotation[..name_end])
+    })
+}
+
+/// Splits a line of annotations such as `@type=int @min=1 @pattern="a b"` into names and values.
+fn parse_annotations(text: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    let mut annotations = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let Some(annotation) = rest.strip_prefix('@') else {
+            return Err(format!(
+                "expected an annotation starting with '@', found '{rest}'"
+            ));
+        };
+        let name_end = annotation
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(annotation.len());
+        let (name, after) = annotation.split_at(name_end);
+        let (value, after) = match after.strip_prefix('=') {
+            Some(quoted) if quoted.starts_with('"') => {
+                let end = quoted[1..]
+                    .find('"')
+                    .ok_or_else(|| format!("unterminated quote in @{name}"))?;
+                (Some(quoted[1..=end].to_owned()), &quoted[end + 2..])
+            }
+            Some(value) => {
+                let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                (Some(value[..end].to_owned()), &value[end..])
+            }
+            None => (None, after),
+        };
+        annotations.push((name.to_owned(), value));
+        rest = after.trim_start();
+    }
+    Ok(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeySchema, Schema, ValueType};
+    use crate::{EnvLoader, EnvMap, EnvSequence, Error, IssueKind, Origin};
+    use std::io::Cursor;
+
+    const SCHEMA: &str = "\
+# The name of the service
+# @required @pattern=\"[a-z][a-z0-9-]*\"
+SERVICE=my-service
+# @type=int @min=1 @max=64
+# @default=4
+WORKERS=
+
+# @see the logging docs
+# @type=bool
+DEBUG=false
+# @type=duration @default=30s
+TIMEOUT=
+# @enum=debug,info
+LOG_LEVEL=info
+";
+
+    fn env_map(vars: &[(&str, &str)]) -> EnvMap {
+        vars.iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn parse() -> Result<(), Error> {
+        let schema = Schema::parse(SCHEMA)?;
+        let keys: Vec<_> = schema.keys().map(KeySchema::key).collect();
+        assert_eq!(
+            keys,
+            ["SERVICE", "WORKERS", "DEBUG", "TIMEOUT", "LOG_LEVEL"]
+        );
+        let workers = schema.keys().nth(1).unwrap();
+        assert_eq!(workers.value_type(), ValueType::Int);
+        assert_eq!(workers.default(), Some("4"));
+        assert_eq!((workers.min(), workers.max()), (Some(1.0), Some(64.0)));
+        assert_eq!(
+            workers.origin(),
+            &Origin::Input {
+                path: None,
+                line: 6
+            }
+        );
+        let service = schema.keys().next().unwrap();
+        assert!(service.is_required());
+        assert_eq!(service.pattern(), Some("[a-z][a-z0-9-]*"));
+
+        for (input, message) in [
+            (
+                "# @type=uint\nA=",
+                "line 1: unknown type 'uint', expected string, int, float, bool, port or duration",
+            ),
+            (
+                "# @max=ten\nA=",
+                "line 1: @max expects a number, found 'ten'",
+            ),
+            (
+                "# @type=float @min=NaN\nA=",
+                "line 1: @min expects a number, found 'NaN'",
+            ),
+            (
+                "# @min=1\nA=",
+                "line 1: @min and @max require a type of int, float or port, not string",
+            ),
+            (
+                "# @required\n# @max=1\nA=",
+                "line 2: @min and @max require a type of int, float or port, not string",
+            ),
+            (
+                "# @type=int @min=1.5\nA=",
+                "line 1: @min and @max must be integers for a type of int",
+            ),
+            ("# @default\nA=", "line 1: @default requires a value"),
+            ("B=\n# @pattern=(\nA=", "line 2: invalid pattern"),
+            (
+                "# @type=int @deprecated\nA=",
+                "line 1: unknown annotation '@deprecated'",
+            ),
+            (
+                "# @type=int oops\nA=",
+                "line 1: expected an annotation starting with '@', found 'oops'",
+            ),
+            ("A=\nB C\n", "line 2: expected a key or a comment"),
+        ] {
+            let err = Schema::parse(input).unwrap_err();
+            assert!(
+                err.to_string().starts_with(message),
+                "{err} does not start with {message}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn validate() -> Result<(), Error> {
+        let schema = Schema::parse(SCHEMA)?;
+        let mut valid = env_map(&[("SERVICE", "api-2"), ("DEBUG", "yes"), ("WORKERS", "")]);
+        schema.apply_defaults(&mut valid);
+        assert_eq!(valid.var("WORKERS")?, "4");
+        assert_eq!(valid.var("TIMEOUT")?, "30s");
+        assert!(schema.validate(&valid).is_empty());
+
+        let invalid = env_map(&[
+            ("WORKERS", "100"),
+            ("DEBUG", "maybe"),
+            ("TIMEOUT", "soon"),
+            ("LOG_LEVEL", "trace"),
+        ]);
+        let report = schema.validate(&invalid);
+        let kinds: Vec<_> = report.issues().iter().map(|issue| &issue.kind).collect();
+        assert!(matches!(kinds[0], IssueKind::Missing));
+        let messages: Vec<_> = kinds[1..]
+            .iter()
+            .map(|kind| match kind {
+                IssueKind::Invalid { message, .. } => message.as_str(),
+                _ => panic!("expected IssueKind::Invalid, got {kind:?}"),
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "expected at most 64",
+                "expected one of true, false, 1, 0, yes, no, on or off",
+                "expected a whole number followed by a unit, such as 30s",
+                "expected one of debug, info",
+            ]
+        );
+
+        let report = schema.validate(&env_map(&[("SERVICE", "My Service")]));
+        assert_eq!(
+            report.to_string(),
+            "error: SERVICE has an invalid value 'My Service': expected a match for the pattern [a-z][a-z0-9-]*"
+        );
+
+        // integers above 2^53 are compared exactly
+        let schema = Schema::parse("# @type=int @max=9007199254740993\nID=")?;
+        assert!(schema
+            .validate(&env_map(&[("ID", "9007199254740993")]))
+            .is_empty());
+        assert_eq!(
+            schema
+                .validate(&env_map(&[("ID", "9007199254740994")]))
+                .to_string(),
+            "error: ID has an invalid value '9007199254740994': expected at most 9007199254740993"
+        );
+
+        let schema = Schema::parse("# @type=float @min=0 @max=1\nRATIO=")?;
+        assert!(schema.validate(&env_map(&[("RATIO", "0.5")])).is_empty());
+        for value in ["NaN", "inf", "-inf"] {
+            let report = schema.validate(&env_map(&[("RATIO", value)]));
+            assert_eq!(
+                report.to_string(),
+                format!("error: RATIO has an invalid value '{value}': expected a finite number")
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn load_with_schema() -> Result<(), Error> {
+        let schema = Schema::parse(SCHEMA)?;
+        let loader = |input: &'static str| {
+            EnvLoader::with_reader(Cursor::new(input))
+                .path(".env")
+                .sequence(EnvSequence::InputOnly)
+        };
+
+        let env_map = loader("SERVICE=api\nDEBUG=on").load_with_schema(&schema)?;
+        assert_eq!(env_map.var("WORKERS")?, "4");
+
+        let err = loader("DEBUG=on\nWORKERS=0\n")
+            .load_with_schema(&schema)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: SERVICE is not set; .env:2: WORKERS has an invalid value '0': expected at least 1"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// A search for an env file in a directory and its parent directories.
+///
+/// The search starts in the current directory unless another is set with `start`,
+/// and moves toward the filesystem root until the file is found.
+/// It can be limited with `stop_at`, e.g. to stay within a repository or workspace.
+///
+/// This is used with [`EnvLoader::with_search`](crate::EnvLoader::with_search).
+/// The file is searched for when loading, and the path that was found is used in errors and provenance.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::{EnvLoader, Search};
+///
+/// let search = Search::new(".env").stop_at_git_root();
+/// println!("found {}", search.find()?.display());
+/// let env_map = EnvLoader::with_search(search).load()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Search {
+    file_name: PathBuf,
+    start: Option<PathBuf>,
+    markers: Vec<OsString>,
+}
+
+impl Search {
+    /// Creates a search for the file name, starting in the current directory and ending at the filesystem root.
+    pub fn new<P: AsRef<Path>>(file_name: P) -> Self {
+        Self {
+            file_name: file_name.as_ref().to_owned(),
+            start: None,
+            markers: Vec::new(),
+        }
+    }
+
+    /// Sets the directory in which the search starts.
+    #[must_use]
+    pub fn start<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.start = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Ends the search at the first directory containing an entry with the name, such as `Cargo.toml`.
+    ///
+    /// That directory is still searched, but its parents are not. This can be called more than once to stop at any of several markers.
+    #[must_use]
+    pub fn stop_at<S: Into<OsString>>(mut self, marker: S) -> Self {
+        self.markers.push(marker.into());
+        self
+    }
+
+    /// Ends the search at the root of the git repository, i.e. the first directory containing `.git`.
+    #[must_use]
+    pub fn stop_at_git_root(self) -> Self {
+        self.stop_at(".git")
+    }
+
+    /// Searches for the file, returning its path.
+    ///
+    /// Fails with `io::ErrorKind::NotFound` if the file is not found before the search ends.
+    pub fn find(&self) -> io::Result<PathBuf> {
+        // a relative start is made absolute so that its parents are searched too,
+        // and normalized so that `..` does not lead back into a directory that is not a parent
+        let start = match &self.start {
+            Some(start) => normalize(&env::current_dir()?.join(start)),
+            None => env::current_dir()?,
+        };
+        let mut dir = start.as_path();
+        loop {
+            let candidate = dir.join(&self.file_name);
+            match fs::metadata(&candidate) {
+                Ok(metadata) if metadata.is_file() => return Ok(candidate),
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+            if self.markers.iter().any(|marker| dir.join(marker).exists()) {
+                break;
+            }
+            match dir.parent() {
+                Some(parent) => dir = parent,
+                None => break,
+            }
+        }
+        Err(io::ErrorKind::NotFound.into())
+    }
+
+    /// Returns the path the file would have in the starting directory, for errors when it is not found.
+    pub(crate) fn start_path(&self) -> PathBuf {
+        self.start
+            .as_deref()
+            .unwrap_or_else(|| Path::new("."))
+            .join(&self.file_name)
+    }
+}
+
+/// Removes `.` and `..` components from an absolute path without accessing the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Search;
+    use crate::{test_util::TempDir, EnvLoader, EnvSequence};
+    use std::{env, fs, io, process};
+
+    #[test]
+    fn find() -> Result<(), crate::Error> {
+        let root = TempDir::new("search");
+        let workspace = root.join("workspace");
+        let member = workspace.join("crates").join("member");
+        fs::create_dir_all(&member).unwrap();
+        root.write(".env", "SEARCH_FOUND=root");
+        root.write("workspace/Cargo.toml", "");
+
+        let search = Search::new(".env").start(&member);
+        assert_eq!(search.find().unwrap(), root.join(".env"));
+        // `..` is resolved before searching, so the directory it leaves is not searched
+        root.write("workspace/crates/member/.env", "SEARCH_FOUND=member");
+        let parent = Search::new(".env").start(member.join(".."));
+        assert_eq!(parent.find().unwrap(), root.join(".env"));
+        fs::remove_file(member.join(".env")).unwrap();
+        let env_map = EnvLoader::with_search(search.clone())
+            .sequence(EnvSequence::InputOnly)
+            .load()?;
+        assert_eq!(env_map.var("SEARCH_FOUND")?, "root");
+        let stopped = search.stop_at("Cargo.toml");
+        assert_eq!(stopped.find().unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        root.write("workspace/.env", "SEARCH_FOUND=workspace\nSEARCH 1");
+        assert_eq!(stopped.find().unwrap(), workspace.join(".env"));
+        let err = EnvLoader::with_search(stopped)
+            .sequence(EnvSequence::InputOnly)
+            .load()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::LineParse { path: Some(path), .. } if path == workspace.join(".env")
+        ));
+
+        drop(root);
+        let missing = Search::new(format!("dotenvy-search-{}.env", process::id())).start(&member);
+        let err = EnvLoader::with_search(missing).load().unwrap_err();
+        assert!(err.not_found());
+        Ok(())
+    }
+
+    #[test]
+    fn find_relative_start() {
+        // tests run in the package directory, and the changelog is in the workspace root
+        let root = env::current_dir().unwrap().parent().unwrap().to_owned();
+        for start in [".", "src", "..", "src/../.."] {
+            let search = Search::new("CHANGELOG.md").start(start);
+            assert_eq!(search.find().unwrap(), root.join("CHANGELOG.md"));
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::EnvMap;
+use std::{env, rc::Rc};
+
+/// A source of values for variable substitution.
+///
+/// When a value references a variable with `$NAME` or `${NAME}`, the source is consulted first.
+/// If the source does not contain the variable, the values defined earlier in the input or in earlier inputs are used.
+///
+/// The default source is [`ProcessEnv`].
+pub trait SubstitutionSource {
+    /// Returns the value of the variable, or `None` if it is unset.
+    fn lookup(&self, key: &str) -> Option<String>;
+
+    /// Creates a source that consults `self`, and then `other` if `self` does not contain the variable.
+    fn chain<S: SubstitutionSource>(self, other: S) -> Chain<Self, S>
+    where
+        Self: Sized,
+    {
+        Chain(self, other)
+    }
+}
+
+/// Substitutes variables from the environment of the current process.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessEnv;
+
+impl SubstitutionSource for ProcessEnv {
+    fn lookup(&self, key: &str) -> Option<String> {
+        env::var(key).ok()
+    }
+}
+
+/// Substitutes only variables defined earlier in the input.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileOnly;
+
+impl SubstitutionSource for FileOnly {
+    fn lookup(&self, _key: &str) -> Option<String> {
+        None
+    }
+}
+
+impl SubstitutionSource for EnvMap {
+    fn lookup(&self, key: &str) -> Option<String> {
+        self.get(key).cloned()
+    }
+}
+
+/// A source that consults two sources in order.
+///
+/// This is created by [`SubstitutionSource::chain`].
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Chain<A, B>(A, B);
+
+impl<A: SubstitutionSource, B: SubstitutionSource> SubstitutionSource for Chain<A, B> {
+    fn lookup(&self, key: &str) -> Option<String> {
+        self.0.lookup(key).or_else(|| self.1.lookup(key))
+    }
+}
+
+impl<S: SubstitutionSource + ?Sized> SubstitutionSource for &S {
+    fn lookup(&self, key: &str) -> Option<String> {
+        (**self).lookup(key)
+    }
+}
+
+impl<S: SubstitutionSource + ?Sized> SubstitutionSource for Box<S> {
+    fn lookup(&self, key: &str) -> Option<String> {
+        (**self).lookup(key)
+    }
+}
+
+impl<S: SubstitutionSource + ?Sized> SubstitutionSource for Rc<S> {
+    fn lookup(&self, key: &str) -> Option<String> {
+        (**self).lookup(key)
+    }
+}
@@ -0,0 +1,48 @@
+use std::{
+    env, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// A directory for the files of a test, which is removed when dropped, even if the test panics.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates an empty directory, named after the test and the process so that tests do not share files.
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("dotenvy-{name}-{}", process::id()));
+        // a directory left by an earlier process with the same id is emptied
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    /// Writes a file in the directory, creating its parent directories, and returns its path.
+    pub fn write<P: AsRef<Path>>(&self, path: P, contents: &str) -> PathBuf {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::EnvMap;
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+impl EnvMap {
+    /// Parses the value of the variable with `FromStr`.
+    ///
+    /// Fails with `Error::NotPresent` if the variable is not set,
+    /// and with `Error::InvalidValue` carrying the key, the value and the reason if it cannot be parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::EnvMap;
+    ///
+    /// let mut env_map = EnvMap::new();
+    /// env_map.insert("PORT".to_owned(), "8080".to_owned());
+    /// env_map.insert("WORKERS".to_owned(), "many".to_owned());
+    ///
+    /// let port: u16 = env_map.parse("PORT")?;
+    /// assert_eq!(port, 8080);
+    ///
+    /// let err = env_map.parse::<u32>("WORKERS").unwrap_err();
+    /// assert_eq!(err.to_string(), "WORKERS has an invalid value 'many': invalid digit found in string");
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn parse<T>(&self, key: &str) -> Result<T, crate::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.parse_with(key, |value| {
+            value.parse().map_err(|e: T::Err| e.to_string())
+        })
+    }
+
+    /// Parses the value of the variable with `FromStr`, or returns `None` if it is not set.
+    ///
+    /// A value that cannot be parsed is still an error.
+    pub fn parse_opt<T>(&self, key: &str) -> Result<Option<T>, crate::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        unset_as_none(self.parse(key))
+    }
+
+    /// Parses the value of the variable with `FromStr`, or returns the default if it is not set.
+    ///
+    /// A value that cannot be parsed is still an error.
+    pub fn parse_or<T>(&self, key: &str, default: T) -> Result<T, crate::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.parse_opt(key).map(|value| value.unwrap_or(default))
+    }
+
+    /// Parses the value of the variable as a list of items separated by the separator, e.g. `a,b,c`.
+    ///
+    /// Whitespace around each item is trimmed, and an empty value is an empty list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::EnvMap;
+    ///
+    /// let mut env_map = EnvMap::new();
+    /// env_map.insert("PORTS".to_owned(), "80, 443".to_owned());
+    ///
+    /// let ports: Vec<u16> = env_map.parse_list("PORTS", ',')?;
+    /// assert_eq!(ports, [80, 443]);
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn parse_list<T>(&self, key: &str, separator: char) -> Result<Vec<T>, crate::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.parse_with(key, |value| {
+            if value.trim().is_empty() {
+                return Ok(Vec::new());
+            }
+            value
+                .split(separator)
+                .map(|item| {
+                    let item = item.trim();
+                    item.parse()
+                        .map_err(|e: T::Err| format!("invalid item '{item}': {e}"))
+                })
+                .collect()
+        })
+    }
+
+    /// Parses the value of the variable as a boolean.
+    ///
+    /// `true`, `1`, `yes` and `on` are true, and `false`, `0`, `no` and `off` are false, ignoring case.
+    pub fn parse_bool(&self, key: &str) -> Result<bool, crate::Error> {
+        self.parse_with(key, parse_bool)
+    }
+
+    /// Parses the value of the variable as a duration, such as `30s`.
+    ///
+    /// The value is a whole number followed by a unit: `ms`, `s`, `m`, `h` or `d`.
+    /// A number without a unit is in seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::EnvMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut env_map = EnvMap::new();
+    /// env_map.insert("TIMEOUT".to_owned(), "500ms".to_owned());
+    ///
+    /// assert_eq!(env_map.parse_duration("TIMEOUT")?, Duration::from_millis(500));
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn parse_duration(&self, key: &str) -> Result<Duration, crate::Error> {
+        self.parse_with(key, parse_duration)
+    }
+
+    /// Parses the value of the variable with the function, which returns the reason a value is invalid.
+    fn parse_with<T, F>(&self, key: &str, parse: F) -> Result<T, crate::Error>
+    where
+        F: FnOnce(&str) -> Result<T, String>,
+    {
+        let value = self.var(key)?;
+        parse(&value).map_err(|message| crate::Error::InvalidValue {
+            key: key.to_owned(),
+            value,
+            message,
+        })
+    }
+}
+
+/// Turns `Error::NotPresent` into `None`.
+fn unset_as_none<T>(result: Result<T, crate::Error>) -> Result<Option<T>, crate::Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(crate::Error::NotPresent(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err("expected one of true, false, 1, 0, yes, no, on or off".to_owned()),
+    }
+}
+
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_start);
+    let number = number
+        .parse::<u64>()
+        .map_err(|_| "expected a whole number followed by a unit, such as 30s".to_owned())?;
+    let seconds = |multiplier: u64| {
+        number
+            .checked_mul(multiplier)
+            .map(Duration::from_secs)
+            .ok_or_else(|| "duration is too long".to_owned())
+    };
+    match unit.trim_start() {
+        "ms" => Ok(Duration::from_millis(number)),
+        "" | "s" => seconds(1),
+        "m" => seconds(60),
+        "h" => seconds(60 * 60),
+        "d" => seconds(24 * 60 * 60),
+        unit => Err(format!("unknown unit '{unit}', expected ms, s, m, h or d")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EnvMap, Error};
+    use std::time::Duration;
+
+    fn env_map(vars: &[(&str, &str)]) -> EnvMap {
+        vars.iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn parse() -> Result<(), Error> {
+        let env_map = env_map(&[("PORT", "8080"), ("OFFSET", "-5"), ("NAME", "app")]);
+        assert_eq!(env_map.parse::<u16>("PORT")?, 8080);
+        assert_eq!(env_map.parse::<i32>("OFFSET")?, -5);
+        assert_eq!(env_map.parse::<String>("NAME")?, "app");
+        assert_eq!(env_map.parse_opt::<u16>("PORT")?, Some(8080));
+        assert_eq!(env_map.parse_opt::<u16>("MISSING")?, None);
+        assert_eq!(env_map.parse_or("MISSING", 3000_u16)?, 3000);
+        assert_eq!(env_map.parse_or("PORT", 3000_u16)?, 8080);
+
+        assert!(matches!(
+            env_map.parse::<u16>("MISSING"),
+            Err(Error::NotPresent(key)) if key == "MISSING"
+        ));
+        let err = env_map.parse_or("NAME", 0_u16).unwrap_err();
+        assert!(matches!(
+            &err,
+            Error::InvalidValue { key, value, message }
+                if key == "NAME" && value == "app" && message == "invalid digit found in string"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_list() -> Result<(), Error> {
+        let env_map = env_map(&[
+            ("HOSTS", "a.com, b.com ,c.com"),
+            ("EMPTY", ""),
+            ("PORTS", "80;x"),
+        ]);
+        assert_eq!(
+            env_map.parse_list::<String>("HOSTS", ',')?,
+            ["a.com", "b.com", "c.com"]
+        );
+        assert!(env_map.parse_list::<u16>("EMPTY", ',')?.is_empty());
+        let err = env_map.parse_list::<u16>("PORTS", ';').unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "PORTS has an invalid value '80;x': invalid item 'x': invalid digit found in string"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_bool() -> Result<(), Error> {
+        let env_map = env_map(&[("A", "TRUE"), ("B", "off"), ("C", "1"), ("D", "maybe")]);
+        assert!(env_map.parse_bool("A")?);
+        assert!(!env_map.parse_bool("B")?);
+        assert!(env_map.parse_bool("C")?);
+        assert!(matches!(
+            env_map.parse_bool("D"),
+            Err(Error::InvalidValue { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_duration() -> Result<(), Error> {
+        let env_map = env_map(&[
+            ("MS", "250ms"),
+            ("S", "30"),
+            ("M", "5 m"),
+            ("D", "1d"),
+            ("UNIT", "5w"),
+            ("NUMBER", "s"),
+            ("LONG", "18446744073709551615d"),
+        ]);
+        assert_eq!(env_map.parse_duration("MS")?, Duration::from_millis(250));
+        assert_eq!(env_map.parse_duration("S")?, Duration::from_secs(30));
+        assert_eq!(env_map.parse_duration("M")?, Duration::from_secs(300));
+        assert_eq!(env_map.parse_duration("D")?, Duration::from_secs(86400));
+        for key in ["UNIT", "NUMBER", "LONG"] {
+            assert!(matches!(
+                env_map.parse_duration(key),
+                Err(Error::InvalidValue { .. })
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+use crate::{EnvMap, Origin, ProvenanceMap};
+use std::fmt;
+
+/// How validation treats a kind of issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// The issue is not reported.
+    Ignore,
+    /// The issue is reported as a warning.
+    Warn,
+    /// The issue is reported as an error.
+    Error,
+}
+
+/// Options for validating variables against an example file such as `.env.example`.
+///
+/// Every key in the example is required. By default, missing keys and empty values are errors,
+/// and keys that are not in the example are warnings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidateOptions {
+    missing: Policy,
+    extra: Policy,
+    empty: Policy,
+}
+
+impl Default for ValidateOptions {
+    fn default() -> Self {
+        Self {
+            missing: Policy::Error,
+            extra: Policy::Warn,
+            empty: Policy::Error,
+        }
+    }
+}
+
+impl ValidateOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how keys in the example that are not set are treated. They are errors by default.
+    #[must_use]
+    pub const fn missing(mut self, policy: Policy) -> Self {
+        self.missing = policy;
+        self
+    }
+
+    /// Sets how keys that are not in the example are treated. They are warnings by default.
+    #[must_use]
+    pub const fn extra(mut self, policy: Policy) -> Self {
+        self.extra = policy;
+        self
+    }
+
+    /// Sets how keys in the example that are set to an empty value are treated. They are errors by default.
+    #[must_use]
+    pub const fn empty(mut self, policy: Policy) -> Self {
+        self.empty = policy;
+        self
+    }
+}
+
+/// The kind of an [`Issue`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IssueKind {
+    /// A key in the example is not set.
+    Missing,
+    /// A key is not in the example.
+    Extra,
+    /// A key in the example is set to an empty value.
+    Empty,
+    /// A value does not satisfy the constraints of a `Schema`. `message` is the reason.
+    Invalid { value: String, message: String },
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Missing => f.write_str("is not set"),
+            Self::Extra => f.write_str("is not in the example"),
+            Self::Empty => f.write_str("is empty"),
+            Self::Invalid { value, message } => {
+                write!(f, "has an invalid value '{value}': {message}")
+            }
+        }
+    }
+}
+
+/// Whether an [`Issue`] is a warning or an error, as set by its [`Policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A problem found by validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub key: String,
+    pub kind: IssueKind,
+    pub severity: Severity,
+    /// Where the key is defined: in the example for a missing key, and in the input otherwise.
+    ///
+    /// This is `None` when validating an `EnvMap`, which does not record where its variables came from.
+    pub origin: Option<Origin>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "{origin}: ")?;
+        }
+        write!(f, "{} {}", self.key, self.kind)
+    }
+}
+
+/// The issues found by validating variables against an example file, in the order they were found.
+///
+/// Missing and empty keys are found in the order of the example, followed by extra keys in the order of the input.
+/// It displays as one line per issue, e.g. `error: .env.example:3: DATABASE_URL is not set`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    #[must_use]
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    /// Returns an iterator over the issues that are errors.
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.with_severity(Severity::Error)
+    }
+
+    /// Returns an iterator over the issues that are warnings.
+    pub fn warnings(&self) -> impl Iterator<Item = &Issue> {
+        self.with_severity(Severity::Warning)
+    }
+
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(move |issue| issue.severity == severity)
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        policy: Policy,
+        key: &str,
+        kind: IssueKind,
+        origin: Option<&Origin>,
+    ) {
+        let severity = match policy {
+            Policy::Ignore => return,
+            Policy::Warn => Severity::Warning,
+            Policy::Error => Severity::Error,
+        };
+        self.issues.push(Issue {
+            key: key.to_owned(),
+            kind,
+            severity,
+            origin: origin.cloned(),
+        });
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}: {issue}", issue.severity)?;
+        }
+        Ok(())
+    }
+}
+
+impl EnvMap {
+    /// Validates the variables against the variables of an example file, such as `.env.example`.
+    ///
+    /// Every key in the example is required, and its value in the example is ignored.
+    /// Every key of the map that is not in the example is extra, so the map should usually be loaded with `EnvSequence::InputOnly`.
+    /// [`EnvLoader::validate`](crate::EnvLoader::validate) loads both files and reports where each key is defined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::{EnvMap, Policy, ValidateOptions};
+    ///
+    /// let mut example = EnvMap::new();
+    /// example.insert("HOST".to_owned(), String::new());
+    /// example.insert("PORT".to_owned(), String::new());
+    ///
+    /// let mut env_map = EnvMap::new();
+    /// env_map.insert("HOST".to_owned(), "localhost".to_owned());
+    /// env_map.insert("DEBUG".to_owned(), "1".to_owned());
+    ///
+    /// let report = env_map.validate(&example, &ValidateOptions::new());
+    /// assert!(report.has_errors());
+    /// assert_eq!(report.to_string(), "error: PORT is not set\nwarning: DEBUG is not in the example");
+    ///
+    /// let options = ValidateOptions::new().missing(Policy::Warn).extra(Policy::Ignore);
+    /// assert!(!env_map.validate(&example, &options).has_errors());
+    /// ```
+    #[must_use]
+    pub fn validate(&self, example: &Self, options: &ValidateOptions) -> ValidationReport {
+        validate(self, None, example, None, options)
+    }
+}
+
+/// Validates the variables against the example, with the origins of both if they were recorded.
+///
+/// With provenance, only variables from the input can be extra, not variables from the existing environment.
+pub fn validate(
+    env_map: &EnvMap,
+    provenance: Option<&ProvenanceMap>,
+    example: &EnvMap,
+    example_provenance: Option<&ProvenanceMap>,
+    options: &ValidateOptions,
+) -> ValidationReport {
+    fn origin<'a>(provenance: Option<&'a ProvenanceMap>, key: &str) -> Option<&'a Origin> {
+        provenance.and_then(|provenance| provenance.origin(key))
+    }
+
+    let mut report = ValidationReport::default();
+    for key in example.keys() {
+        match env_map.get(key) {
+            None => report.push(
+                options.missing,
+                key,
+                IssueKind::Missing,
+                origin(example_provenance, key),
+            ),
+            Some(value) if value.is_empty() => report.push(
+                options.empty,
+                key,
+                IssueKind::Empty,
+                origin(provenance, key),
+            ),
+            Some(_) => {}
+        }
+    }
+    for key in env_map.keys() {
+        if example.contains_key(key) {
+            continue;
+        }
+        let origin = origin(provenance, key);
+        if provenance.is_none() || matches!(origin, Some(Origin::Input { .. })) {
+            report.push(options.extra, key, IssueKind::Extra, origin);
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IssueKind, Policy, Severity, ValidateOptions};
+    use crate::{test_util::TempDir, EnvLoader, EnvSequence, Error, Origin};
+
+    #[test]
+    fn validate() -> Result<(), Error> {
+        let dir = TempDir::new("validate");
+        let example = dir.write(
+            ".env.example",
+            "# required\nVALIDATE_HOST=\nVALIDATE_PORT=8080\nVALIDATE_KEY=${SECRET}\n",
+        );
+        let path = dir.write(
+            ".env",
+            "VALIDATE_HOST=localhost\nVALIDATE_KEY=\nVALIDATE_DEBUG=1\n",
+        );
+
+        let loader = || EnvLoader::with_path(&path).sequence(EnvSequence::InputOnly);
+        let err = loader()
+            .validate(&example, &ValidateOptions::new())
+            .unwrap_err();
+        let Error::Validation(report) = &err else {
+            panic!("expected Error::Validation, got {err:?}");
+        };
+        let issues: Vec<_> = report
+            .issues()
+            .iter()
+            .map(|issue| (issue.key.as_str(), issue.kind.clone(), issue.severity))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                ("VALIDATE_PORT", IssueKind::Missing, Severity::Error),
+                ("VALIDATE_KEY", IssueKind::Empty, Severity::Error),
+                ("VALIDATE_DEBUG", IssueKind::Extra, Severity::Warning),
+            ]
+        );
+        assert_eq!(
+            report.issues()[0].origin,
+            Some(Origin::Input {
+                path: Some(example.clone()),
+                line: 3
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}:3: VALIDATE_PORT is not set; {}:2: VALIDATE_KEY is empty",
+                example.display(),
+                path.display()
+            )
+        );
+
+        let options = ValidateOptions::new()
+            .missing(Policy::Warn)
+            .empty(Policy::Ignore)
+            .extra(Policy::Warn);
+        let (env_map, report) = loader().validate(&example, &options)?;
+        assert_eq!(env_map.var("VALIDATE_HOST")?, "localhost");
+        assert!(!report.has_errors());
+        assert_eq!(report.warnings().count(), 2);
+
+        // Variables from the existing environment are neither missing nor extra.
+        let report = temp_env::with_var("VALIDATE_PORT", Some("80"), || {
+            EnvLoader::with_path(&path)
+                .validate(&example, &options)
+                .map(|(_, report)| report)
+        })?;
+        assert_eq!(report.issues().len(), 1);
+        assert_eq!(report.issues()[0].key, "VALIDATE_DEBUG");
+        Ok(())
+    }
+}
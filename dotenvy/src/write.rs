@@ -0,0 +1,227 @@
+use crate::{parse, quote, EnvMap, ParseErrorKind};
+use std::io::{self, Write};
+
+/// Options for writing an [`EnvMap`] in env file syntax.
+///
+/// By default, keys are sorted and not prefixed with `export`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    sorted: bool,
+    export: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            sorted: true,
+            export: false,
+        }
+    }
+}
+
+impl WriteOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sorts the keys so that the output is deterministic. It is enabled by default.
+    ///
+    /// When disabled, keys are written in the order of the map.
+    #[must_use]
+    pub const fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Prefixes each line with `export `, so that the output can be sourced by a shell. It is disabled by default.
+    #[must_use]
+    pub const fn export(mut self, export: bool) -> Self {
+        self.export = export;
+        self
+    }
+}
+
+impl EnvMap {
+    /// Writes the variables in env file syntax, one per line.
+    ///
+    /// Each value is written with the minimal quoting needed for it to be loaded back exactly:
+    /// without quotes if possible, in single quotes if it contains no `'`, `\` or control characters,
+    /// and in double quotes with escapes otherwise. `$` is always quoted or escaped, so values are never substituted.
+    ///
+    /// Fails with `Error::LineParse` if a key cannot be written, e.g. because it contains `-`,
+    /// or if a value contains a NUL character, which cannot be loaded into an environment variable.
+    /// Its line is the line the variable would have been written on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::{EnvMap, WriteOptions};
+    ///
+    /// let mut env_map = EnvMap::new();
+    /// env_map.insert("NAME".to_owned(), "my app".to_owned());
+    /// env_map.insert("HOST".to_owned(), "localhost".to_owned());
+    ///
+    /// let mut output = Vec::new();
+    /// env_map.write_to(&mut output, &WriteOptions::new().export(true))?;
+    /// assert_eq!(
+    ///     String::from_utf8(output).unwrap(),
+    ///     "export HOST=localhost\nexport NAME='my app'\n"
+    /// );
+    /// # Ok::<(), dotenvy::Error>(())
+    /// ```
+    pub fn write_to<W: Write>(
+        &self,
+        mut writer: W,
+        options: &WriteOptions,
+    ) -> Result<(), crate::Error> {
+        let mut entries = self.iter().collect::<Vec<_>>();
+        if options.sorted {
+            entries.sort_unstable_by_key(|&(key, _)| key);
+        }
+        let prefix = if options.export { "export " } else { "" };
+
+        for (index, (key, value)) in entries.into_iter().enumerate() {
+            if !parse::is_valid_key(key) {
+                return Err(crate::Error::LineParse {
+                    kind: ParseErrorKind::InvalidKey,
+                    path: None,
+                    line: index + 1,
+                    column: prefix.len() + 1,
+                });
+            }
+            if value.contains('\0') {
+                return Err(crate::Error::LineParse {
+                    kind: ParseErrorKind::InvalidEscape,
+                    path: None,
+                    line: index + 1,
+                    column: prefix.len() + key.len() + 2,
+                });
+            }
+            writeln!(writer, "{prefix}{key}={}", quote::quote(value)).map_err(io_err)?;
+        }
+        writer.flush().map_err(io_err)
+    }
+}
+
+const fn io_err(e: io::Error) -> crate::Error {
+    crate::Error::Io(e, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EnvLoader, EnvMap, EnvSequence, Error, FileOnly, ParseErrorKind, WriteOptions};
+    use proptest::{collection::vec, prelude::*};
+    use std::io::Cursor;
+
+    fn write(env_map: &EnvMap, options: &WriteOptions) -> Result<String, Error> {
+        let mut output = Vec::new();
+        env_map.write_to(&mut output, options)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    fn load(input: String) -> Result<EnvMap, Error> {
+        EnvLoader::with_reader(Cursor::new(input))
+            .sequence(EnvSequence::InputOnly)
+            .substitution_source(FileOnly)
+            .load()
+    }
+
+    #[test]
+    fn write_to() -> Result<(), Error> {
+        let env_map = [
+            ("C", "it's $HOME"),
+            ("A", ""),
+            ("B.b", "# not a comment"),
+            ("_D", "multi\nline"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect::<EnvMap>();
+
+        let output = write(&env_map, &WriteOptions::new())?;
+        assert_eq!(
+            output,
+            "A=\nB.b='# not a comment'\nC=\"it's \\$HOME\"\n_D=\"multi\\nline\"\n"
+        );
+        assert_eq!(load(output)?, env_map);
+
+        let output = write(&env_map, &WriteOptions::new().sorted(false))?;
+        assert!(output.starts_with("C=\"it's \\$HOME\"\nA=\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn write_invalid_key() {
+        let mut env_map = EnvMap::new();
+        env_map.insert("A".to_owned(), "1".to_owned());
+        env_map.insert("ProgramFiles(x86)".to_owned(), "C:\\".to_owned());
+        let result = write(&env_map, &WriteOptions::new().export(true));
+        assert!(matches!(
+            result,
+            Err(Error::LineParse {
+                kind: ParseErrorKind::InvalidKey,
+                path: None,
+                line: 2,
+                column: 8
+            })
+        ));
+
+        let mut env_map = EnvMap::new();
+        env_map.insert("NUL".to_owned(), "a\0b".to_owned());
+        let result = write(&env_map, &WriteOptions::new());
+        assert!(matches!(
+            result,
+            Err(Error::LineParse {
+                kind: ParseErrorKind::InvalidEscape,
+                path: None,
+                line: 1,
+                column: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn write_to_export_special_characters() -> Result<(), Error> {
+        let env_map = [
+            ("DM06D_", "Àቛ\\j𐏋𐠒ꨛT🕴B<:𐦀ã<𞺯\u{cca}"),
+            ("_", "=𞟼7'`¥1Ѩ/:𖿢9(*.*d`ਮ𑰗(ﬆ𐮛:jb\u{11300}*{_"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect::<EnvMap>();
+
+        let output = write(&env_map, &WriteOptions::new().export(true))?;
+        assert!(output.starts_with("export DM06D_=\""));
+        assert_eq!(load(output)?, env_map);
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(
+            entries in vec(("[A-Za-z_][A-Za-z0-9_.]{0,8}", "[^\0]*"), 0..8),
+            sorted: bool,
+            export: bool,
+        ) {
+            let env_map = entries.into_iter().collect::<EnvMap>();
+            let options = WriteOptions::new().sorted(sorted).export(export);
+            let output = write(&env_map, &options).unwrap();
+            let loaded = load(output).unwrap();
+            if !sorted {
+                prop_assert!(loaded.keys().eq(env_map.keys()));
+            }
+            prop_assert_eq!(loaded, env_map);
+        }
+
+        #[test]
+        fn round_trip_special_characters(
+            value in "[ -~\\t\\n\\r\\\\'\"$#{}]{0,16}",
+        ) {
+            let mut env_map = EnvMap::new();
+            env_map.insert("KEY".to_owned(), value);
+            let output = write(&env_map, &WriteOptions::new()).unwrap();
+            prop_assert_eq!(load(output).unwrap(), env_map);
+        }
+    }
+}